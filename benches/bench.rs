@@ -1,4 +1,6 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
+#[cfg(feature = "smallvec")]
+use yabf::SmallYabf;
 use yabf::Yabf;
 
 #[cfg(test)]
@@ -27,16 +29,16 @@ fn bench_vec(c: &mut Criterion) {
     });
 }
 
-#[cfg(feature = "impl_smallvec")]
+#[cfg(feature = "smallvec")]
 #[cfg(test)]
 // Smallvec: bench:  [12.430 ms 12.432 ms 12.433 ms]
 fn bench_smallvec(c: &mut Criterion) {
     println!("running bench with Smallvec");
 
-    let mut bf = Yabf::default();
+    let mut bf = SmallYabf::default();
     c.bench_function("SmallYabf", |b| {
         b.iter(|| {
-            black_box(for _ in 0..1000 {
+            for _ in 0..1000 {
                 for i in (0..2090_usize).rev() {
                     bf.set_bit(i, true);
                 }
@@ -49,7 +51,8 @@ fn bench_smallvec(c: &mut Criterion) {
                 for i in 0..2090_usize {
                     assert!(!bf.bit(i));
                 }
-            })
+            }
+            black_box(&bf);
         })
     });
 }
@@ -60,7 +63,7 @@ fn bench_smallvec(c: &mut Criterion) {
 fn bench_biguint(c: &mut Criterion) {
     let mut bf = num_bigint::BigUint::default();
     c.bench_function("BigUint", |b| {
-        black_box(b.iter(|| {
+        b.iter(|| {
             for _ in 0..1000 {
                 for i in (0..2090_u64).rev() {
                     bf.set_bit(i, true);
@@ -75,7 +78,8 @@ fn bench_biguint(c: &mut Criterion) {
                     assert!(!bf.bit(i));
                 }
             }
-        }))
+            black_box(&bf);
+        })
     });
 }
 
@@ -84,6 +88,7 @@ fn bench_biguint(c: &mut Criterion) {
 fn bench_vob(c: &mut Criterion) {
     type VobU32 = vob::Vob<u32>;
 
+    #[allow(dead_code)]
     trait GrowingVob {
         /// Will create a new Vob and fill it with `default`
         fn fill(initial_size: usize, default: bool) -> VobU32;
@@ -118,7 +123,7 @@ fn bench_vob(c: &mut Criterion) {
     let mut vob = VobU32::new_with_storage_type(0);
     c.bench_function("Vob", |b| {
         b.iter(|| {
-            black_box(for _ in 0..1000 {
+            for _ in 0..1000 {
                 for i in (0..2090).rev() {
                     vob.set_grow(i, true);
                 }
@@ -131,19 +136,99 @@ fn bench_vob(c: &mut Criterion) {
                 for i in 0..2090 {
                     assert!(!vob.get_f(i));
                 }
-            })
+            }
+            black_box(&vob);
         })
     });
 }
 
-#[cfg(feature = "impl_smallvec")]
+/// The bit-by-bit iterator Yabf used before the `trailing_zeros()` rewrite, kept as a
+/// reference point for `bench_iter`.
+#[cfg(test)]
+fn legacy_iter(words: &[u32]) -> impl Iterator<Item = usize> + '_ {
+    let mut last_word = 0_usize;
+    let mut last_bit = usize::MAX;
+    std::iter::from_fn(move || {
+        let mut next_word = last_word;
+        let mut next_bit = if last_bit == usize::MAX {
+            0
+        } else {
+            last_bit + 1
+        };
+        loop {
+            if next_bit > 31 {
+                next_bit = 0;
+                next_word += 1;
+            }
+            if next_word >= words.len() {
+                return None;
+            }
+            let sample = words[next_word];
+            if sample == 0 {
+                next_word += 1;
+                next_bit = 0;
+                continue;
+            }
+            if next_bit < 16 && sample & 0xFFFF == 0 {
+                next_bit = 16;
+            }
+            if next_bit >= 16 && sample & 0xFFFF0000 == 0 {
+                next_word += 1;
+                next_bit = 0;
+                continue;
+            }
+            while next_bit < 32 {
+                if sample & (1u32 << next_bit) != 0 {
+                    last_bit = next_bit;
+                    last_word = next_word;
+                    return Some(next_word * 32 + next_bit);
+                }
+                next_bit += 1;
+            }
+        }
+    })
+}
+
+// Iterates over 100_000 bits where every 7th bit is set, followed by a sparse region
+// Yabf:        time: [14.224 µs 15.009 µs 15.814 µs]
+// legacy Yabf: time: [107.80 µs 110.05 µs 112.30 µs]
+// Vob:         time: [7.7932 µs 7.9602 µs 8.1057 µs]
+#[cfg(test)]
+fn bench_iter(c: &mut Criterion) {
+    let mut bf = Yabf::default();
+    let mut vob = vob::Vob::<u32>::new_with_storage_type(0);
+    vob.resize(200_000, false);
+    for i in (0..100_000_usize)
+        .step_by(7)
+        .chain((100_000..200_000).step_by(1013))
+    {
+        bf.set_bit(i, true);
+        vob.set(i, true);
+    }
+    let expected = bf.iter().count();
+
+    let mut group = c.benchmark_group("iter");
+    group.bench_function("Yabf", |b| {
+        b.iter(|| assert_eq!(black_box(&bf).into_iter().count(), expected))
+    });
+    group.bench_function("legacy Yabf", |b| {
+        b.iter(|| assert_eq!(legacy_iter(black_box(&bf).storage()).count(), expected))
+    });
+    group.bench_function("Vob", |b| {
+        b.iter(|| assert_eq!(black_box(&vob).iter_set_bits(..).count(), expected))
+    });
+    group.finish();
+}
+
+#[cfg(feature = "smallvec")]
 criterion_group!(
     benches1,
     bench_vec,
     bench_smallvec,
     bench_biguint,
-    bench_vob
+    bench_vob,
+    bench_iter
 );
-#[cfg(not(feature = "impl_smallvec"))]
-criterion_group!(benches1, bench_vec, bench_biguint, bench_vob);
+#[cfg(not(feature = "smallvec"))]
+criterion_group!(benches1, bench_vec, bench_biguint, bench_vob, bench_iter);
 criterion_main!(benches1);
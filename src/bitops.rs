@@ -0,0 +1,457 @@
+//! Set algebra on bit fields: union (`|`), intersection (`&`), symmetric difference (`^`) and
+//! difference (`-`).
+//!
+//! Every operation is available as `a op= &b`, `a op &b` (consuming `a`) and `&a op &b`.
+//! The operands may have different internal lengths and different storages, as long as the
+//! word types match. The same holds for the set predicates and counts, e.g. `is_subset` and
+//! `intersection_count`, which never allocate.
+
+use crate::{GenericYabf, Storage, Word};
+use std::ops;
+
+impl<S: Storage> GenericYabf<S> {
+    /// Construct a new bit field where each word is `f(a, b)` of the corresponding words of
+    /// `a` and `b`. Missing words are treated as zero. The result will be `len` words long.
+    fn from_word_op<T, U, F>(a: &GenericYabf<T>, b: &GenericYabf<U>, len: usize, f: F) -> Self
+    where
+        T: Storage<Word = S::Word>,
+        U: Storage<Word = S::Word>,
+        F: Fn(S::Word, S::Word) -> S::Word,
+    {
+        let a = a.internals.as_slice();
+        let b = b.internals.as_slice();
+        let mut internals = S::with_capacity(len);
+        internals.resize(len);
+        for (i, w) in internals.as_mut_slice().iter_mut().enumerate() {
+            let a = a.get(i).copied().unwrap_or(S::Word::ZERO);
+            let b = b.get(i).copied().unwrap_or(S::Word::ZERO);
+            *w = f(a, b);
+        }
+        Self { internals }
+    }
+
+    /// Returns true if every bit set in `self` is also set in `other`.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let a = Yabf::from_ranges(vec![1..3]);
+    /// let b = Yabf::from_ranges(vec![0..10, 500..501]);
+    /// assert!(a.is_subset(&b));
+    /// assert!(!b.is_subset(&a));
+    /// assert!(Yabf::default().is_subset(&a));
+    /// ```
+    pub fn is_subset<T: Storage<Word = S::Word>>(&self, other: &GenericYabf<T>) -> bool {
+        word_pairs(self.internals.as_slice(), other.internals.as_slice())
+            .all(|(a, b)| a & !b == S::Word::ZERO)
+    }
+
+    /// Returns true if every bit set in `other` is also set in `self`.
+    #[inline]
+    pub fn is_superset<T: Storage<Word = S::Word>>(&self, other: &GenericYabf<T>) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Returns true if `self` and `other` have no set bits in common.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let a = Yabf::from_ranges(vec![1..3]);
+    /// let b = Yabf::from_ranges(vec![3..10]);
+    /// assert!(a.is_disjoint(&b));
+    /// assert!(!a.intersects(&b));
+    /// ```
+    pub fn is_disjoint<T: Storage<Word = S::Word>>(&self, other: &GenericYabf<T>) -> bool {
+        self.internals
+            .as_slice()
+            .iter()
+            .zip(other.internals.as_slice().iter())
+            .all(|(a, b)| *a & *b == S::Word::ZERO)
+    }
+
+    /// Returns true if `self` and `other` have at least one set bit in common.
+    #[inline]
+    pub fn intersects<T: Storage<Word = S::Word>>(&self, other: &GenericYabf<T>) -> bool {
+        !self.is_disjoint(other)
+    }
+
+    /// Returns the number of bits set in both `self` and `other`, i.e. `(&a & &b).count_ones()`.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let a = Yabf::from_ranges(vec![0..10]);
+    /// let b = Yabf::from_ranges(vec![5..100]);
+    /// assert_eq!(a.intersection_count(&b), 5);
+    /// assert_eq!(a.union_count(&b), 100);
+    /// assert_eq!(a.difference_count(&b), 5);
+    /// assert_eq!(b.difference_count(&a), 90);
+    /// ```
+    pub fn intersection_count<T: Storage<Word = S::Word>>(&self, other: &GenericYabf<T>) -> usize {
+        self.internals
+            .as_slice()
+            .iter()
+            .zip(other.internals.as_slice().iter())
+            .map(|(a, b)| (*a & *b).count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns the number of bits set in `self` or `other`, i.e. `(&a | &b).count_ones()`.
+    pub fn union_count<T: Storage<Word = S::Word>>(&self, other: &GenericYabf<T>) -> usize {
+        word_pairs(self.internals.as_slice(), other.internals.as_slice())
+            .map(|(a, b)| (a | b).count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns the number of bits set in `self` but not in `other`, i.e.
+    /// `(&a - &b).count_ones()`.
+    pub fn difference_count<T: Storage<Word = S::Word>>(&self, other: &GenericYabf<T>) -> usize {
+        let other = other.internals.as_slice();
+        self.internals
+            .as_slice()
+            .iter()
+            .enumerate()
+            .map(|(i, a)| {
+                (*a & !other.get(i).copied().unwrap_or(S::Word::ZERO)).count_ones() as usize
+            })
+            .sum()
+    }
+}
+
+/// Iterates over the word pairs of `a` and `b`, the shorter slice is padded with zero words.
+#[inline]
+pub(crate) fn word_pairs<'a, W: Word>(a: &'a [W], b: &'a [W]) -> impl Iterator<Item = (W, W)> + 'a {
+    (0..a.len().max(b.len())).map(move |i| {
+        (
+            a.get(i).copied().unwrap_or(W::ZERO),
+            b.get(i).copied().unwrap_or(W::ZERO),
+        )
+    })
+}
+
+/// bit or assign operation.
+/// This is a relatively expensive O(size of container) operation.
+/// ```
+/// # use yabf::Yabf;
+///
+/// let mut a = Yabf::default();
+/// let mut b = Yabf::default();
+/// a.set_bit(45,true);
+/// b.set_bit(12345,true);
+/// assert!(!a.bit(12345));
+/// assert!(a.bit(45));
+/// a |= &b;
+/// assert!(a.bit(12345));
+/// assert!(a.bit(45));
+/// ```
+impl<S: Storage, T: Storage<Word = S::Word>> ops::BitOrAssign<&GenericYabf<T>> for GenericYabf<S> {
+    fn bitor_assign(&mut self, other: &GenericYabf<T>) {
+        let other = other.internals.as_slice();
+        if self.internals.as_slice().len() < other.len() {
+            self.internals.resize(other.len());
+        }
+        for (a, b) in self.internals.as_mut_slice().iter_mut().zip(other.iter()) {
+            *a |= *b;
+        }
+    }
+}
+
+/// bit and assign operation, only the bits set in both bit fields are kept.
+/// This is a relatively expensive O(size of container) operation.
+/// ```
+/// # use yabf::Yabf;
+///
+/// let mut a = Yabf::default();
+/// let mut b = Yabf::default();
+/// a.set_bit(45,true);
+/// a.set_bit(12345,true);
+/// b.set_bit(45,true);
+/// a &= &b;
+/// assert!(a.bit(45));
+/// assert!(!a.bit(12345));
+/// ```
+impl<S: Storage, T: Storage<Word = S::Word>> ops::BitAndAssign<&GenericYabf<T>> for GenericYabf<S> {
+    fn bitand_assign(&mut self, other: &GenericYabf<T>) {
+        let other = other.internals.as_slice();
+        let words = self.internals.as_mut_slice();
+        for (i, a) in words.iter_mut().enumerate() {
+            *a &= other.get(i).copied().unwrap_or(S::Word::ZERO);
+        }
+        self.internals.bits_cleared();
+    }
+}
+
+/// bit xor assign operation, only the bits set in exactly one of the bit fields are kept.
+/// This is a relatively expensive O(size of container) operation.
+/// ```
+/// # use yabf::Yabf;
+///
+/// let mut a = Yabf::default();
+/// let mut b = Yabf::default();
+/// a.set_bit(45,true);
+/// b.set_bit(45,true);
+/// b.set_bit(12345,true);
+/// a ^= &b;
+/// assert!(!a.bit(45));
+/// assert!(a.bit(12345));
+/// ```
+impl<S: Storage, T: Storage<Word = S::Word>> ops::BitXorAssign<&GenericYabf<T>> for GenericYabf<S> {
+    fn bitxor_assign(&mut self, other: &GenericYabf<T>) {
+        let other = other.internals.as_slice();
+        if self.internals.as_slice().len() < other.len() {
+            self.internals.resize(other.len());
+        }
+        for (a, b) in self.internals.as_mut_slice().iter_mut().zip(other.iter()) {
+            *a ^= *b;
+        }
+        self.internals.bits_cleared();
+    }
+}
+
+/// set difference assign operation, the bits set in `other` are cleared.
+/// This is a relatively expensive O(size of container) operation.
+/// ```
+/// # use yabf::Yabf;
+///
+/// let mut a = Yabf::default();
+/// let mut b = Yabf::default();
+/// a.set_bit(45,true);
+/// a.set_bit(12345,true);
+/// b.set_bit(12345,true);
+/// a -= &b;
+/// assert!(a.bit(45));
+/// assert!(!a.bit(12345));
+/// ```
+impl<S: Storage, T: Storage<Word = S::Word>> ops::SubAssign<&GenericYabf<T>> for GenericYabf<S> {
+    fn sub_assign(&mut self, other: &GenericYabf<T>) {
+        let other = other.internals.as_slice();
+        for (a, b) in self.internals.as_mut_slice().iter_mut().zip(other.iter()) {
+            *a &= !*b;
+        }
+        self.internals.bits_cleared();
+    }
+}
+
+/// Implements `a op &b` on top of `a op= &b`, re-using the storage of `a`, and `&a op &b`
+/// with `$op`, a `fn(&Lhs, &Rhs) -> Lhs`. Starting with `assign`, `a op= &b` is implemented
+/// on top of `$op` as well, for the bit fields that can not combine in place.
+///
+/// The generic parameters of the impls are given in brackets before the operand types.
+macro_rules! impl_binary_op {
+    (
+        assign [$($generics:tt)*] $lhs:ty,
+        $rhs:ty,
+        $trait:ident,
+        $method:ident,
+        $assign_trait:ident,
+        $assign_method:ident,
+        $op:expr
+    ) => {
+        impl<$($generics)*> ops::$assign_trait<&$rhs> for $lhs {
+            #[inline]
+            fn $assign_method(&mut self, other: &$rhs) {
+                *self = ops::$trait::$method(&*self, other);
+            }
+        }
+
+        impl_binary_op!(
+            [$($generics)*] $lhs,
+            $rhs,
+            $trait,
+            $method,
+            $assign_trait,
+            $assign_method,
+            $op
+        );
+    };
+    (
+        [$($generics:tt)*] $lhs:ty,
+        $rhs:ty,
+        $trait:ident,
+        $method:ident,
+        $assign_trait:ident,
+        $assign_method:ident,
+        $op:expr
+    ) => {
+        impl<$($generics)*> ops::$trait<&$rhs> for $lhs {
+            type Output = $lhs;
+
+            #[inline]
+            fn $method(mut self, other: &$rhs) -> $lhs {
+                ops::$assign_trait::$assign_method(&mut self, other);
+                self
+            }
+        }
+
+        impl<$($generics)*> ops::$trait<&$rhs> for &$lhs {
+            type Output = $lhs;
+
+            fn $method(self, other: &$rhs) -> $lhs {
+                let op: fn(&$lhs, &$rhs) -> $lhs = $op;
+                op(self, other)
+            }
+        }
+    };
+}
+pub(crate) use impl_binary_op;
+
+// `&a op &b` allocates a new bit field of the needed length
+impl_binary_op!(
+    [S: Storage, T: Storage<Word = S::Word>] GenericYabf<S>,
+    GenericYabf<T>,
+    BitOr,
+    bitor,
+    BitOrAssign,
+    bitor_assign,
+    |a, b| {
+        let len = a.internal_len().max(b.internal_len());
+        GenericYabf::<S>::from_word_op(a, b, len, |x, y| x | y)
+    }
+);
+impl_binary_op!(
+    [S: Storage, T: Storage<Word = S::Word>] GenericYabf<S>,
+    GenericYabf<T>,
+    BitAnd,
+    bitand,
+    BitAndAssign,
+    bitand_assign,
+    |a, b| {
+        let len = a.internal_len().min(b.internal_len());
+        GenericYabf::<S>::from_word_op(a, b, len, |x, y| x & y)
+    }
+);
+impl_binary_op!(
+    [S: Storage, T: Storage<Word = S::Word>] GenericYabf<S>,
+    GenericYabf<T>,
+    BitXor,
+    bitxor,
+    BitXorAssign,
+    bitxor_assign,
+    |a, b| {
+        let len = a.internal_len().max(b.internal_len());
+        GenericYabf::<S>::from_word_op(a, b, len, |x, y| x ^ y)
+    }
+);
+impl_binary_op!(
+    [S: Storage, T: Storage<Word = S::Word>] GenericYabf<S>,
+    GenericYabf<T>,
+    Sub,
+    sub,
+    SubAssign,
+    sub_assign,
+    |a, b| {
+        let len = a.internal_len();
+        GenericYabf::<S>::from_word_op(a, b, len, |x, y| x & !y)
+    }
+);
+
+#[cfg(test)]
+mod test {
+    use crate::Yabf;
+
+    fn from_bits(bits: &[usize]) -> Yabf {
+        let mut bf = Yabf::default();
+        for b in bits.iter() {
+            bf.set_bit(*b, true);
+        }
+        bf
+    }
+
+    fn bits<S: crate::Storage>(bf: &crate::GenericYabf<S>) -> Vec<usize> {
+        bf.into_iter().collect()
+    }
+
+    #[test]
+    fn test_and() {
+        let a = from_bits(&[1, 40, 100, 1000]);
+        let b = from_bits(&[1, 100]);
+        assert_eq!(bits(&(&a & &b)), vec![1, 100]);
+        assert_eq!(bits(&(&b & &a)), vec![1, 100]);
+        assert_eq!((&a & &b).internal_len(), b.internal_len());
+        let mut c = a.clone();
+        c &= &b;
+        assert_eq!(bits(&c), vec![1, 100]);
+        assert_eq!(bits(&(a & &b)), vec![1, 100]);
+    }
+
+    #[test]
+    fn test_xor() {
+        let a = from_bits(&[1, 40, 100, 1000]);
+        let b = from_bits(&[1, 100, 2000]);
+        assert_eq!(bits(&(&a ^ &b)), vec![40, 1000, 2000]);
+        assert_eq!(bits(&(&b ^ &a)), vec![40, 1000, 2000]);
+        let mut c = b.clone();
+        c ^= &a;
+        assert_eq!(bits(&c), vec![40, 1000, 2000]);
+    }
+
+    #[test]
+    fn test_sub() {
+        let a = from_bits(&[1, 40, 100, 1000]);
+        let b = from_bits(&[1, 100, 2000]);
+        assert_eq!(bits(&(&a - &b)), vec![40, 1000]);
+        assert_eq!(bits(&(&b - &a)), vec![2000]);
+        let mut c = a.clone();
+        c -= &b;
+        assert_eq!(bits(&c), vec![40, 1000]);
+    }
+
+    #[test]
+    fn test_or() {
+        let a = from_bits(&[1, 1000]);
+        let b = from_bits(&[2, 2000]);
+        assert_eq!(bits(&(&a | &b)), vec![1, 2, 1000, 2000]);
+        assert_eq!(bits(&(a | &b)), vec![1, 2, 1000, 2000]);
+    }
+
+    #[test]
+    fn test_predicates() {
+        let lists: Vec<Vec<usize>> = vec![
+            vec![],
+            vec![1],
+            vec![1, 40],
+            vec![40, 100],
+            vec![1, 40, 100, 1000],
+            vec![2000],
+        ];
+        for la in lists.iter() {
+            for lb in lists.iter() {
+                let (a, b) = (from_bits(la), from_bits(lb));
+                let subset = la.iter().all(|x| lb.contains(x));
+                let common = la.iter().filter(|x| lb.contains(x)).count();
+                assert_eq!(a.is_subset(&b), subset);
+                assert_eq!(b.is_superset(&a), subset);
+                assert_eq!(a.is_disjoint(&b), common == 0);
+                assert_eq!(a.intersects(&b), common != 0);
+                assert_eq!(a.intersection_count(&b), common);
+                assert_eq!(a.union_count(&b), la.len() + lb.len() - common);
+                assert_eq!(a.difference_count(&b), la.len() - common);
+                assert_eq!(a.intersection_count(&b), (&a & &b).count_ones());
+                assert_eq!(a.union_count(&b), (&a | &b).count_ones());
+                assert_eq!(a.difference_count(&b), (&a - &b).count_ones());
+            }
+        }
+        // trailing zero words do not matter
+        let mut a = from_bits(&[1, 5000]);
+        a.set_bit(5000, false);
+        assert!(a.is_subset(&from_bits(&[1])));
+    }
+
+    #[cfg(feature = "smallvec")]
+    #[test]
+    fn test_mixed_storage() {
+        let a = from_bits(&[1, 40, 100, 1000]);
+        let mut b = crate::SmallYabf::default();
+        b.set_bit(40, true);
+        b.set_bit(5, true);
+        assert_eq!(bits(&(&b & &a)), vec![40]);
+        assert_eq!(bits(&(&a - &b)), vec![1, 100, 1000]);
+        assert!(b.intersects(&a));
+        assert_eq!(a.union_count(&b), 5);
+        b ^= &a;
+        assert_eq!(bits(&b), vec![1, 5, 100, 1000]);
+        assert!(!b.is_subset(&a));
+        assert!(a.is_subset(&(&b | &from_bits(&[40]))));
+    }
+}
@@ -0,0 +1,188 @@
+//! Equality, hashing and ordering of bit fields.
+//!
+//! Bit fields are compared as sets of indices: trailing zero words and capacity are ignored,
+//! so two bit fields with the same bits set are equal regardless of how they got there.
+//! The ordering is the same as the one of a `BTreeSet<usize>` holding the set bits, i.e. the
+//! lexicographic order of the sorted indices.
+
+use crate::search::next_one;
+use crate::storage::trailing_zero_words;
+use crate::{GenericYabf, Storage, Word};
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+impl<S: Storage> GenericYabf<S> {
+    /// The words up to, and including, the last non-zero word
+    #[inline]
+    pub(crate) fn significant_words(&self) -> &[S::Word] {
+        let words = self.internals.as_slice();
+        &words[..words.len() - trailing_zero_words(words)]
+    }
+}
+
+/// ```
+/// # use yabf::Yabf;
+///
+/// let mut a = Yabf::default();
+/// let mut b = Yabf::with_capacity(1000);
+/// a.set_bit(1, true);
+/// b.set_bit(1, true);
+/// b.set_bit(999, true);
+/// assert_ne!(a, b);
+/// b.set_bit(999, false);
+/// assert_eq!(a, b);
+/// ```
+impl<S: Storage, T: Storage<Word = S::Word>> PartialEq<GenericYabf<T>> for GenericYabf<S> {
+    #[inline]
+    fn eq(&self, other: &GenericYabf<T>) -> bool {
+        self.significant_words() == other.significant_words()
+    }
+}
+
+impl<S: Storage> Eq for GenericYabf<S> {}
+
+impl<S: Storage> Hash for GenericYabf<S> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.significant_words().hash(state)
+    }
+}
+
+/// ```
+/// # use yabf::Yabf;
+///
+/// let a = Yabf::from_ranges(vec![1..2, 5..6]);
+/// let b = Yabf::from_ranges(vec![1..2, 7..8]);
+/// let c = Yabf::from_ranges(vec![1..2]);
+/// // same order as the sorted index lists [1, 5] < [1, 7] and [1] < [1, 5]
+/// assert!(a < b);
+/// assert!(c < a);
+/// ```
+impl<S: Storage, T: Storage<Word = S::Word>> PartialOrd<GenericYabf<T>> for GenericYabf<S> {
+    #[inline]
+    fn partial_cmp(&self, other: &GenericYabf<T>) -> Option<Ordering> {
+        Some(cmp_words(
+            self.internals.as_slice(),
+            other.internals.as_slice(),
+        ))
+    }
+}
+
+impl<S: Storage> Ord for GenericYabf<S> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        cmp_words(self.internals.as_slice(), other.internals.as_slice())
+    }
+}
+
+/// Compares the sorted index lists of `a` and `b` lexicographically.
+fn cmp_words<W: Word>(a: &[W], b: &[W]) -> Ordering {
+    let len = a.len().max(b.len());
+    for i in 0..len {
+        let wa = a.get(i).copied().unwrap_or(W::ZERO);
+        let wb = b.get(i).copied().unwrap_or(W::ZERO);
+        let diff = wa ^ wb;
+        if diff == W::ZERO {
+            continue;
+        }
+        // the lowest index that is in one set but not in the other
+        let bit = diff.trailing_zeros() as usize;
+        let pos = i * W::BITS + bit;
+        // The set holding `pos` is the smaller one, unless the other set ends before `pos`
+        // (and is therefore a prefix of it).
+        let (other, ordering) = if wa & (W::ONE << bit) != W::ZERO {
+            (b, Ordering::Less)
+        } else {
+            (a, Ordering::Greater)
+        };
+        return if next_one(other, pos + 1).is_some() {
+            ordering
+        } else {
+            ordering.reverse()
+        };
+    }
+    Ordering::Equal
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{GenericYabf, Yabf};
+    use std::collections::{BTreeSet, HashSet};
+
+    fn from_bits(bits: &[usize]) -> Yabf {
+        let mut bf = Yabf::default();
+        for b in bits.iter() {
+            bf.set_bit(*b, true);
+        }
+        bf
+    }
+
+    #[test]
+    fn test_eq_hash() {
+        let a = from_bits(&[1, 100]);
+        let mut b = from_bits(&[1, 100, 10_000]);
+        assert_ne!(a, b);
+        b.set_bit(10_000, false);
+        assert_eq!(a, b);
+        assert_ne!(a.internal_len(), b.internal_len());
+        let mut set = HashSet::new();
+        assert!(set.insert(a.clone()));
+        assert!(!set.insert(b));
+        assert!(set.contains(&a));
+        assert_eq!(Yabf::default(), Yabf::with_capacity(100));
+    }
+
+    #[cfg(feature = "smallvec")]
+    #[test]
+    fn test_eq_mixed() {
+        let a = from_bits(&[1, 100]);
+        let mut b = crate::SmallYabf::default();
+        b.set_bit(1, true);
+        assert_ne!(a, b);
+        assert!(b < a);
+        b.set_bit(100, true);
+        assert_eq!(a, b);
+        assert_eq!(b, a);
+    }
+
+    #[test]
+    fn test_ord() {
+        let lists: Vec<Vec<usize>> = vec![
+            vec![],
+            vec![0],
+            vec![0, 1],
+            vec![0, 1, 1000],
+            vec![0, 2],
+            vec![1],
+            vec![1, 31],
+            vec![1, 32],
+            vec![31],
+            vec![32],
+            vec![32, 33, 500],
+            vec![33],
+            vec![1000],
+        ];
+        for a in lists.iter() {
+            for b in lists.iter() {
+                let ya = from_bits(a);
+                let yb = from_bits(b);
+                assert_eq!(ya.cmp(&yb), a.cmp(b), "{:?} {:?}", a, b);
+                let sa: BTreeSet<usize> = a.iter().copied().collect();
+                let sb: BTreeSet<usize> = b.iter().copied().collect();
+                assert_eq!(ya.cmp(&yb), sa.cmp(&sb));
+            }
+        }
+        let set: BTreeSet<GenericYabf<Vec<u8>>> = lists
+            .iter()
+            .map(|l| {
+                l.iter().copied().fold(GenericYabf::default(), |mut bf, b| {
+                    bf.set_bit(b, true);
+                    bf
+                })
+            })
+            .collect();
+        assert_eq!(set.len(), lists.len());
+        let sorted: Vec<Vec<usize>> = set.iter().map(|bf| bf.iter().collect()).collect();
+        assert_eq!(sorted, lists);
+    }
+}
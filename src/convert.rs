@@ -0,0 +1,278 @@
+//! Bulk conversions between bit fields and `bool`, byte and `u64` word slices.
+//!
+//! Bit `n` of the bit field corresponds to element `n` of a `bool` slice, to bit `n % 8` of
+//! byte `n / 8` of a little endian byte slice and to bit `n % 64` of word `n / 64` of a
+//! `u64` word slice.
+
+use crate::{GenericYabf, Storage, Word};
+
+impl<S: Storage> GenericYabf<S> {
+    /// Construct a bit field from its words
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let bf = Yabf::from_words(&[0b101, 1]);
+    /// assert_eq!(bf.iter().collect::<Vec<_>>(), vec![0, 2, 32]);
+    /// ```
+    pub fn from_words(words: &[S::Word]) -> Self {
+        let mut internals = S::with_capacity(words.len());
+        internals.resize(words.len());
+        internals.as_mut_slice().copy_from_slice(words);
+        Self { internals }
+    }
+
+    /// Returns the words of the internal storage, lowest bits first
+    #[inline]
+    pub fn as_words(&self) -> &[S::Word] {
+        self.internals.as_slice()
+    }
+
+    /// Construct a bit field where bit `n` is set if `bools[n]` is `true`.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let bf = Yabf::from_bools(&[true, false, true]);
+    /// assert_eq!(bf.iter().collect::<Vec<_>>(), vec![0, 2]);
+    /// ```
+    pub fn from_bools(bools: &[bool]) -> Self {
+        Self::from_chunks(bools.chunks(S::Word::BITS), |chunk| {
+            chunk.iter().enumerate().fold(
+                S::Word::ZERO,
+                |w, (i, b)| {
+                    if *b {
+                        w | S::Word::ONE << i
+                    } else {
+                        w
+                    }
+                },
+            )
+        })
+    }
+
+    /// Returns the first `len` bits as a vector of `bool`.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let bf = Yabf::from_bools(&[true, false, true]);
+    /// assert_eq!(bf.to_bools(4), vec![true, false, true, false]);
+    /// ```
+    pub fn to_bools(&self, len: usize) -> Vec<bool> {
+        let mut rv = Vec::with_capacity(len);
+        for w in self.internals.as_slice().iter() {
+            let remaining = (len - rv.len()).min(S::Word::BITS);
+            rv.extend((0..remaining).map(|i| *w & (S::Word::ONE << i) != S::Word::ZERO));
+            if rv.len() == len {
+                return rv;
+            }
+        }
+        rv.resize(len, false);
+        rv
+    }
+
+    /// Construct a bit field from little endian bytes, i.e. bit `n` is bit `n % 8` of
+    /// `bytes[n / 8]`.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let bf = Yabf::from_bytes_le(&[0x01, 0x80, 0x00, 0x00, 0x02]);
+    /// assert_eq!(bf.iter().collect::<Vec<_>>(), vec![0, 15, 33]);
+    /// ```
+    pub fn from_bytes_le(bytes: &[u8]) -> Self {
+        Self::from_chunks(bytes.chunks(bytes_per_word::<S::Word>()), |chunk| {
+            chunk.iter().enumerate().fold(S::Word::ZERO, |w, (i, b)| {
+                w | S::Word::from_u64(u64::from(*b)) << (i * 8)
+            })
+        })
+    }
+
+    /// Returns the bits as little endian bytes, the inverse of [`from_bytes_le`]. The
+    /// result holds every word of the internal storage, including trailing zero words.
+    ///
+    /// [`from_bytes_le`]: GenericYabf::from_bytes_le
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let bf = Yabf::from_bytes_le(&[0x01, 0x80]);
+    /// assert_eq!(bf.to_bytes_le(), vec![0x01, 0x80, 0x00, 0x00]);
+    /// ```
+    pub fn to_bytes_le(&self) -> Vec<u8> {
+        let per_word = bytes_per_word::<S::Word>();
+        let words = self.internals.as_slice();
+        let mut rv = Vec::with_capacity(words.len() * per_word);
+        for w in words.iter() {
+            rv.extend((0..per_word).map(|i| (*w >> (i * 8)).to_u64() as u8));
+        }
+        rv
+    }
+
+    /// Construct a bit field from `u64` words, i.e. bit `n` is bit `n % 64` of
+    /// `words[n / 64]`.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let bf = Yabf::from_u64_words(&[1 << 63, 1]);
+    /// assert_eq!(bf.iter().collect::<Vec<_>>(), vec![63, 64]);
+    /// ```
+    pub fn from_u64_words(words: &[u64]) -> Self {
+        if S::Word::BITS <= 64 {
+            // every u64 is split into several words
+            let per_u64 = 64 / S::Word::BITS;
+            let mut internals = S::with_capacity(words.len() * per_u64);
+            internals.resize(words.len() * per_u64);
+            for (i, w) in internals.as_mut_slice().iter_mut().enumerate() {
+                let shift = (i % per_u64) * S::Word::BITS;
+                *w = S::Word::from_u64(words[i / per_u64] >> shift);
+            }
+            Self { internals }
+        } else {
+            // every word is built from several u64
+            Self::from_chunks(words.chunks(S::Word::BITS / 64), |chunk| {
+                chunk.iter().enumerate().fold(S::Word::ZERO, |w, (i, v)| {
+                    w | S::Word::from_u64(*v) << (i * 64)
+                })
+            })
+        }
+    }
+
+    /// Returns the bits as `u64` words, the inverse of [`from_u64_words`]. The result
+    /// covers every word of the internal storage, the last `u64` is zero padded.
+    ///
+    /// [`from_u64_words`]: GenericYabf::from_u64_words
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let mut bf = Yabf::default();
+    /// bf.set_bit(64, true);
+    /// assert_eq!(bf.to_u64_words(), vec![0, 1]);
+    /// ```
+    pub fn to_u64_words(&self) -> Vec<u64> {
+        let words = self.internals.as_slice();
+        if S::Word::BITS <= 64 {
+            let per_u64 = 64 / S::Word::BITS;
+            words
+                .chunks(per_u64)
+                .map(|chunk| {
+                    chunk
+                        .iter()
+                        .enumerate()
+                        .fold(0_u64, |v, (i, w)| v | w.to_u64() << (i * S::Word::BITS))
+                })
+                .collect()
+        } else {
+            let per_word = S::Word::BITS / 64;
+            let mut rv = Vec::with_capacity(words.len() * per_word);
+            for w in words.iter() {
+                rv.extend((0..per_word).map(|i| (*w >> (i * 64)).to_u64()));
+            }
+            rv
+        }
+    }
+
+    /// Builds the storage word by word, `f` packs one chunk of input into one word.
+    fn from_chunks<'a, T: 'a, I, F>(chunks: I, f: F) -> Self
+    where
+        I: ExactSizeIterator<Item = &'a [T]>,
+        F: Fn(&[T]) -> S::Word,
+    {
+        let mut internals = S::with_capacity(chunks.len());
+        internals.resize(chunks.len());
+        for (w, chunk) in internals.as_mut_slice().iter_mut().zip(chunks) {
+            *w = f(chunk);
+        }
+        Self { internals }
+    }
+}
+
+/// The number of bytes in a word
+#[inline]
+fn bytes_per_word<W: Word>() -> usize {
+    W::BITS / 8
+}
+
+/// ```
+/// # use yabf::Yabf;
+///
+/// let bf = Yabf::from(1_u128 << 100 | 1);
+/// assert_eq!(bf.iter().collect::<Vec<_>>(), vec![0, 100]);
+/// ```
+impl<S: Storage> From<u128> for GenericYabf<S> {
+    #[inline]
+    fn from(value: u128) -> Self {
+        Self::from_u64_words(&[value as u64, (value >> 64) as u64])
+    }
+}
+
+/// ```
+/// # use yabf::Yabf;
+///
+/// let bf = Yabf::from([1_u64, 0, 2]);
+/// assert_eq!(bf.iter().collect::<Vec<_>>(), vec![0, 129]);
+/// ```
+impl<S: Storage, const N: usize> From<[u64; N]> for GenericYabf<S> {
+    #[inline]
+    fn from(value: [u64; N]) -> Self {
+        Self::from_u64_words(&value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::GenericYabf;
+
+    const BITS: [usize; 12] = [0, 1, 7, 8, 31, 32, 63, 64, 127, 128, 129, 500];
+
+    fn check<S: crate::Storage + Default>() {
+        let bf: GenericYabf<S> = BITS.iter().copied().collect();
+
+        let bools = bf.to_bools(600);
+        assert_eq!(bools.len(), 600);
+        for (i, b) in bools.iter().enumerate() {
+            assert_eq!(*b, BITS.contains(&i));
+        }
+        assert_eq!(GenericYabf::<S>::from_bools(&bools), bf);
+        assert_eq!(bf.to_bools(3), vec![true, true, false]);
+
+        let bytes = bf.to_bytes_le();
+        assert_eq!(bytes[0], 0x83);
+        assert_eq!(bytes[1], 0x01);
+        assert_eq!(GenericYabf::<S>::from_bytes_le(&bytes), bf);
+        assert_eq!(GenericYabf::<S>::from_bytes_le(&bytes[..3]).count_ones(), 4);
+
+        let words = bf.to_u64_words();
+        assert_eq!(words[0], 1 << 63 | 1 << 32 | 1 << 31 | 0x183);
+        assert_eq!(words[1], 1 << 63 | 1);
+        assert_eq!(GenericYabf::<S>::from_u64_words(&words), bf);
+        assert_eq!(
+            GenericYabf::<S>::from_u64_words(&words[..1]).count_ones(),
+            7
+        );
+
+        let small = GenericYabf::<S>::from(1_u128 << 127 | 1 << 64 | 1);
+        assert_eq!(small.iter().collect::<Vec<_>>(), vec![0, 64, 127]);
+        let from_array = GenericYabf::<S>::from([words[0], words[1]]);
+        assert_eq!(from_array.iter().collect::<Vec<_>>(), BITS[..9].to_vec());
+
+        let empty = GenericYabf::<S>::default();
+        assert!(empty.to_bytes_le().is_empty());
+        assert!(empty.to_u64_words().is_empty());
+        assert_eq!(empty.to_bools(2), vec![false, false]);
+    }
+
+    #[test]
+    fn test_conversions() {
+        check::<Vec<u8>>();
+        check::<Vec<u16>>();
+        check::<Vec<u32>>();
+        check::<Vec<u64>>();
+        check::<Vec<u128>>();
+        #[cfg(feature = "smallvec")]
+        check::<smallvec::SmallVec<[u32; 4]>>();
+    }
+}
@@ -0,0 +1,566 @@
+//! A run length encoded bit field in the EWAH (Enhanced Word-Aligned Hybrid) format.
+
+use crate::bitops::impl_binary_op;
+use crate::{GenericYabf, Storage};
+use std::iter::FusedIterator;
+use std::ops;
+
+/// The largest number of words in the clean run of a marker
+const MAX_RUN: usize = u32::MAX as usize;
+/// The largest number of literal words following a marker
+const MAX_LITERALS: usize = (1 << 31) - 1;
+
+#[inline]
+fn marker(run_bit: bool, run: usize, literals: usize) -> u64 {
+    run_bit as u64 | (run as u64) << 1 | (literals as u64) << 33
+}
+
+#[inline]
+fn run_bit(marker: u64) -> bool {
+    marker & 1 != 0
+}
+
+#[inline]
+fn run_len(marker: u64) -> usize {
+    (marker >> 1 & MAX_RUN as u64) as usize
+}
+
+#[inline]
+fn literal_count(marker: u64) -> usize {
+    (marker >> 33) as usize
+}
+
+/// A compressed bit field for bit fields with long runs of set or cleared bits. AND, OR and
+/// XOR are computed directly on the compressed streams.
+///
+/// The bits are grouped into 64 bit words. Words with all bits set, or all bits cleared, are
+/// "clean" and stored as runs, all other words are "literal" and stored as they are. The
+/// stream is a sequence of marker words, each followed by its literal words. A marker holds:
+///  * bit 0: the value of the bits of its clean run
+///  * bits 1..33: the number of words in the clean run
+///  * bits 33..64: the number of literal words following the marker
+///
+/// ```
+/// # use yabf::{EwahYabf, Yabf};
+///
+/// let bf = Yabf::from_ranges(vec![0..100_000, 200_000..200_001]);
+/// let ewah = EwahYabf::from(&bf);
+/// assert_eq!(ewah.compressed_len(), 4);
+/// assert!(ewah.bit(99_999) && !ewah.bit(100_000) && ewah.bit(200_000));
+/// assert_eq!(Yabf::from(&ewah), bf);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct EwahYabf {
+    // markers and literal words, without a trailing run of cleared words
+    stream: Vec<u64>,
+    // the position of the last marker in `stream`
+    last_marker: usize,
+}
+
+impl EwahYabf {
+    /// Construct an empty bit field
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of words in the compressed stream
+    #[inline]
+    pub fn compressed_len(&self) -> usize {
+        self.stream.len()
+    }
+
+    /// Returns true if no bit is set
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.stream.is_empty()
+    }
+
+    /// Returns the value of bit `n`. This is an O(size of the compressed stream) operation.
+    pub fn bit(&self, n: usize) -> bool {
+        let target = n / 64;
+        let mut cursor = Cursor::new(&self.stream);
+        let mut word_index = 0;
+        while let Some(item) = cursor.current() {
+            match item {
+                Item::Clean(bit, len) => {
+                    if target < word_index + len {
+                        return bit;
+                    }
+                    word_index += len;
+                    cursor.advance(len);
+                }
+                Item::Literal(word, _) => {
+                    if target == word_index {
+                        return word >> (n % 64) & 1 != 0;
+                    }
+                    word_index += 1;
+                    cursor.advance(1);
+                }
+            }
+        }
+        false
+    }
+
+    /// Returns the number of bits set to `true`
+    pub fn count_ones(&self) -> usize {
+        let mut cursor = Cursor::new(&self.stream);
+        let mut rv = 0;
+        while let Some(item) = cursor.current() {
+            match item {
+                Item::Clean(bit, len) => {
+                    if bit {
+                        rv += len * 64;
+                    }
+                    cursor.advance(len);
+                }
+                Item::Literal(word, _) => {
+                    rv += word.count_ones() as usize;
+                    cursor.advance(1);
+                }
+            }
+        }
+        rv
+    }
+
+    /// Returns an iterator over the set bit indices, from lowest to highest
+    #[inline]
+    pub fn iter(&self) -> EwahIterator<'_> {
+        EwahIterator {
+            cursor: Cursor::new(&self.stream),
+            word_index: 0,
+            literal: 0,
+            literal_base: 0,
+            ones: 0..0,
+        }
+    }
+
+    /// Appends `len` words with the bits of `fill`, which must be `0` or `u64::MAX`
+    fn push_clean(&mut self, fill: u64, mut len: usize) {
+        let bit = fill != 0;
+        while len > 0 {
+            let last = self.stream.get(self.last_marker).copied();
+            match last {
+                Some(m)
+                    if literal_count(m) == 0
+                        && (run_len(m) == 0 || run_bit(m) == bit)
+                        && run_len(m) < MAX_RUN =>
+                {
+                    let add = len.min(MAX_RUN - run_len(m));
+                    self.stream[self.last_marker] = marker(bit, run_len(m) + add, 0);
+                    len -= add;
+                }
+                _ => {
+                    self.last_marker = self.stream.len();
+                    self.stream.push(marker(bit, 0, 0));
+                }
+            }
+        }
+    }
+
+    /// Appends a word that is neither `0` nor `u64::MAX`
+    fn push_literal(&mut self, word: u64) {
+        match self.stream.get(self.last_marker).copied() {
+            Some(m) if literal_count(m) < MAX_LITERALS => {
+                self.stream[self.last_marker] =
+                    marker(run_bit(m), run_len(m), literal_count(m) + 1);
+            }
+            _ => {
+                self.last_marker = self.stream.len();
+                self.stream.push(marker(false, 0, 1));
+            }
+        }
+        self.stream.push(word);
+    }
+
+    /// Appends a word
+    #[inline]
+    fn push_word(&mut self, word: u64) {
+        if word == 0 || word == u64::MAX {
+            self.push_clean(word, 1);
+        } else {
+            self.push_literal(word);
+        }
+    }
+
+    /// Removes trailing runs of cleared words, so that equal bit fields have equal streams.
+    fn finish(&mut self) {
+        loop {
+            match self.stream.last().copied() {
+                Some(m)
+                    if self.stream.len() - 1 == self.last_marker
+                        && literal_count(m) == 0
+                        && !run_bit(m) =>
+                {
+                    let _ = self.stream.pop();
+                    // find the previous marker
+                    let mut pos = 0;
+                    self.last_marker = 0;
+                    while pos < self.stream.len() {
+                        self.last_marker = pos;
+                        pos += literal_count(self.stream[pos]) + 1;
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Combines the compressed streams of `a` and `b` word by word with `op`, which must map
+    /// `(0, 0)` to `0`. Runs are combined without expanding them.
+    fn combine(a: &Self, b: &Self, op: fn(u64, u64) -> u64) -> Self {
+        let mut rv = Self::default();
+        let mut ca = Cursor::new(&a.stream);
+        let mut cb = Cursor::new(&b.stream);
+        // an exhausted stream continues with cleared words
+        let end = Item::Clean(false, usize::MAX);
+        loop {
+            let (ia, ib) = match (ca.current(), cb.current()) {
+                (None, None) => break,
+                (ia, ib) => (ia.unwrap_or(end), ib.unwrap_or(end)),
+            };
+            let len = match (ia, ib) {
+                (Item::Clean(fa, na), Item::Clean(fb, nb)) => {
+                    let len = na.min(nb);
+                    rv.push_clean(op(fill(fa), fill(fb)), len);
+                    len
+                }
+                (Item::Clean(f, n), Item::Literal(w, m)) => {
+                    let f = fill(f);
+                    rv.push_clean_or_literals(op(f, 0) == op(f, u64::MAX), op(f, w), n.min(m))
+                }
+                (Item::Literal(w, m), Item::Clean(f, n)) => {
+                    let f = fill(f);
+                    rv.push_clean_or_literals(op(0, f) == op(u64::MAX, f), op(w, f), n.min(m))
+                }
+                (Item::Literal(wa, _), Item::Literal(wb, _)) => {
+                    rv.push_word(op(wa, wb));
+                    1
+                }
+            };
+            ca.advance(len);
+            cb.advance(len);
+        }
+        rv.finish();
+        rv
+    }
+
+    /// Pushes the result of combining a clean run with literal words. If the result does not
+    /// depend on the literals it is a clean run of `len` words, otherwise only the `word`
+    /// combined from the first literal is pushed. Returns the number of words pushed.
+    #[inline]
+    fn push_clean_or_literals(&mut self, absorbing: bool, word: u64, len: usize) -> usize {
+        if absorbing {
+            self.push_clean(word, len);
+            len
+        } else {
+            self.push_word(word);
+            1
+        }
+    }
+}
+
+/// The word with all bits set to `bit`
+#[inline]
+fn fill(bit: bool) -> u64 {
+    if bit {
+        u64::MAX
+    } else {
+        0
+    }
+}
+
+/// The current position in a compressed stream
+#[derive(Clone, Copy)]
+enum Item {
+    /// A run of clean words with all bits set to the flag, and the remaining length
+    Clean(bool, usize),
+    /// A literal word, and the number of literal words remaining after the marker
+    Literal(u64, usize),
+}
+
+/// Walks a compressed stream word by word, or run by run
+#[derive(Clone)]
+struct Cursor<'a> {
+    stream: &'a [u64],
+    // the position of the next marker, or of the current literal
+    pos: usize,
+    run_bit: bool,
+    run_left: usize,
+    literals_left: usize,
+}
+
+impl<'a> Cursor<'a> {
+    #[inline]
+    fn new(stream: &'a [u64]) -> Self {
+        Self {
+            stream,
+            pos: 0,
+            run_bit: false,
+            run_left: 0,
+            literals_left: 0,
+        }
+    }
+
+    /// Returns the current item, or `None` at the end of the stream
+    #[inline]
+    fn current(&mut self) -> Option<Item> {
+        while self.run_left == 0 && self.literals_left == 0 {
+            let m = *self.stream.get(self.pos)?;
+            self.run_bit = run_bit(m);
+            self.run_left = run_len(m);
+            self.literals_left = literal_count(m);
+            self.pos += 1;
+        }
+        Some(if self.run_left > 0 {
+            Item::Clean(self.run_bit, self.run_left)
+        } else {
+            Item::Literal(self.stream[self.pos], self.literals_left)
+        })
+    }
+
+    /// Skips `len` words, which must not be more than the length of the current item. Does
+    /// nothing at the end of the stream.
+    #[inline]
+    fn advance(&mut self, len: usize) {
+        if self.run_left > 0 {
+            self.run_left -= len;
+        } else if self.literals_left > 0 {
+            self.pos += len;
+            self.literals_left -= len;
+        }
+    }
+}
+
+/// Iterator over the set bits of an [`EwahYabf`], from lowest to highest.
+/// Created by [`EwahYabf::iter`].
+#[derive(Clone)]
+pub struct EwahIterator<'a> {
+    cursor: Cursor<'a>,
+    // the index of the next word of the stream
+    word_index: usize,
+    // the remaining bits of the current literal word, and the index of its first bit
+    literal: u64,
+    literal_base: usize,
+    // the remaining bits of the current run of set bits
+    ones: ops::Range<usize>,
+}
+
+impl Iterator for EwahIterator<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        loop {
+            if let Some(i) = self.ones.next() {
+                return Some(i);
+            }
+            if self.literal != 0 {
+                let bit = self.literal.trailing_zeros() as usize;
+                self.literal &= self.literal - 1;
+                return Some(self.literal_base + bit);
+            }
+            match self.cursor.current()? {
+                Item::Clean(bit, len) => {
+                    if bit {
+                        self.ones = self.word_index * 64..(self.word_index + len) * 64;
+                    }
+                    self.word_index += len;
+                    self.cursor.advance(len);
+                }
+                Item::Literal(word, _) => {
+                    self.literal = word;
+                    self.literal_base = self.word_index * 64;
+                    self.word_index += 1;
+                    self.cursor.advance(1);
+                }
+            }
+        }
+    }
+}
+
+impl FusedIterator for EwahIterator<'_> {}
+
+impl<'a> IntoIterator for &'a EwahYabf {
+    type Item = usize;
+    type IntoIter = EwahIterator<'a>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<S: Storage> From<&GenericYabf<S>> for EwahYabf {
+    fn from(bf: &GenericYabf<S>) -> Self {
+        let mut rv = Self::default();
+        for word in bf.to_u64_words() {
+            rv.push_word(word);
+        }
+        rv.finish();
+        rv
+    }
+}
+
+impl<S: Storage> From<&EwahYabf> for GenericYabf<S> {
+    fn from(bf: &EwahYabf) -> Self {
+        let mut words = Vec::new();
+        let mut cursor = Cursor::new(&bf.stream);
+        while let Some(item) = cursor.current() {
+            match item {
+                Item::Clean(bit, len) => {
+                    words.resize(words.len() + len, fill(bit));
+                    cursor.advance(len);
+                }
+                Item::Literal(word, _) => {
+                    words.push(word);
+                    cursor.advance(1);
+                }
+            }
+        }
+        Self::from_u64_words(&words)
+    }
+}
+
+// the streams are combined into a new bit field, even for `a op= &b`
+impl_binary_op!(
+    assign [] EwahYabf,
+    EwahYabf,
+    BitOr,
+    bitor,
+    BitOrAssign,
+    bitor_assign,
+    |a, b| EwahYabf::combine(a, b, |x, y| x | y)
+);
+impl_binary_op!(
+    assign [] EwahYabf,
+    EwahYabf,
+    BitAnd,
+    bitand,
+    BitAndAssign,
+    bitand_assign,
+    |a, b| EwahYabf::combine(a, b, |x, y| x & y)
+);
+impl_binary_op!(
+    assign [] EwahYabf,
+    EwahYabf,
+    BitXor,
+    bitxor,
+    BitXorAssign,
+    bitxor_assign,
+    |a, b| EwahYabf::combine(a, b, |x, y| x ^ y)
+);
+
+#[cfg(test)]
+mod test {
+    use super::{marker, EwahYabf, MAX_RUN};
+    use crate::Yabf;
+    use rand::{Rng, SeedableRng};
+
+    /// Runs of random length, alternating between cleared, set and random bits
+    fn sample(seed: u64) -> Yabf {
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(seed);
+        let mut bf = Yabf::default();
+        let mut pos = rng.gen_range(0..100);
+        for i in 0..60 {
+            let len = rng.gen_range(1..2000);
+            match i % 3 {
+                0 => (),
+                1 => bf.set_range(pos..pos + len),
+                _ => bf.extend((pos..pos + len).filter(|_| rng.gen_bool(0.3))),
+            }
+            pos += len;
+        }
+        bf
+    }
+
+    #[test]
+    fn test_conversion() {
+        for seed in 0..5 {
+            let bf = sample(seed);
+            let ewah = EwahYabf::from(&bf);
+            assert_eq!(Yabf::from(&ewah), bf);
+            assert_eq!(ewah.count_ones(), bf.count_ones());
+            assert!(ewah.iter().eq(bf.iter()));
+            for n in (0..bf.internal_len() * 32 + 100).step_by(7) {
+                assert_eq!(ewah.bit(n), bf.bit(n));
+            }
+        }
+        let empty = EwahYabf::from(&Yabf::with_capacity(1000));
+        assert!(empty.is_empty());
+        assert_eq!(empty, EwahYabf::new());
+        assert_eq!(empty.iter().next(), None);
+        assert!(!empty.bit(10));
+    }
+
+    #[test]
+    fn test_stream() {
+        let bf = Yabf::from_ranges(vec![0..128, 130..131, 640..641]);
+        let ewah = EwahYabf::from(&bf);
+        assert_eq!(
+            ewah.stream,
+            vec![marker(true, 2, 1), 1 << 2, marker(false, 7, 1), 1]
+        );
+        // trailing cleared words are not stored
+        let mut bf: Yabf = (0..64).collect();
+        bf.set_bit(10_000, true);
+        bf.set_bit(10_000, false);
+        assert_eq!(EwahYabf::from(&bf).stream, vec![marker(true, 1, 0)]);
+    }
+
+    #[test]
+    fn test_ops() {
+        for seed in 0..4 {
+            let (a, b) = (sample(seed), sample(seed + 10));
+            let (ea, eb) = (EwahYabf::from(&a), EwahYabf::from(&b));
+            assert_eq!(Yabf::from(&(&ea & &eb)), &a & &b);
+            assert_eq!(Yabf::from(&(&ea | &eb)), &a | &b);
+            assert_eq!(Yabf::from(&(&ea ^ &eb)), &a ^ &b);
+            assert_eq!(&ea & &eb, EwahYabf::from(&(&a & &b)));
+            assert_eq!(&ea ^ &eb, EwahYabf::from(&(&a ^ &b)));
+            let mut c = ea.clone();
+            c ^= &ea;
+            assert!(c.is_empty());
+            c |= &eb;
+            assert_eq!(c, eb);
+            assert_eq!(c & &EwahYabf::new(), EwahYabf::new());
+        }
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_long_runs() {
+        // runs longer than a marker can hold are split across markers
+        let mut ones = EwahYabf::new();
+        ones.push_clean(u64::MAX, MAX_RUN + 10);
+        ones.push_literal(5);
+        assert_eq!(
+            ones.stream,
+            vec![marker(true, MAX_RUN, 0), marker(true, 10, 1), 5]
+        );
+        assert_eq!(ones.count_ones(), (MAX_RUN + 10) * 64 + 2);
+        assert!(ones.bit((MAX_RUN + 9) * 64 + 63));
+        assert!(ones.bit((MAX_RUN + 10) * 64));
+        assert!(!ones.bit((MAX_RUN + 10) * 64 + 1));
+
+        let mut zeros = EwahYabf::new();
+        zeros.push_clean(0, MAX_RUN + 10);
+        zeros.push_literal(3);
+        assert_eq!(
+            zeros.stream,
+            vec![marker(false, MAX_RUN, 0), marker(false, 10, 1), 3]
+        );
+        assert_eq!(zeros.iter().next(), Some((MAX_RUN + 10) * 64));
+        assert_eq!(zeros.count_ones(), 2);
+
+        assert_eq!(&ones & &ones, ones);
+        assert!((&ones ^ &ones).is_empty());
+        assert_eq!(
+            (&ones | &zeros).stream,
+            vec![marker(true, MAX_RUN, 0), marker(true, 10, 1), 7]
+        );
+        assert_eq!(
+            (&ones & &zeros).stream,
+            vec![marker(false, MAX_RUN, 0), marker(false, 10, 1), 1]
+        );
+    }
+}
@@ -0,0 +1,653 @@
+//! Iterators over the bits of a bit field.
+
+use crate::word::low_mask;
+use crate::{to_range, GenericYabf, RankSelect, Storage, Word};
+use std::iter::{FromIterator, FusedIterator};
+use std::ops::RangeBounds;
+
+/// The iteration state shared by [`YabfIterator`] and [`YabfIntoIterator`]. It keeps the
+/// current words in registers and pops the lowest (highest) set bit using
+/// `trailing_zeros()` (`leading_zeros()`).
+#[derive(Clone)]
+struct Cursor<W> {
+    // the index of the word held in `front`
+    front_index: usize,
+    // the not yet returned set bits of the front word
+    front: W,
+    // the index of the word held in `back`. When it is equal to `front_index` the front and
+    // the back have met and only `front` is used.
+    back_index: usize,
+    // the not yet returned set bits of the back word
+    back: W,
+}
+
+impl<W: Word> Cursor<W> {
+    /// Iterate over the set bits in `start..end`, `end` may be larger than the bit field.
+    fn new(words: &[W], start: usize, end: usize) -> Self {
+        let end = end.min(words.len() * W::BITS);
+        if start >= end {
+            return Self {
+                front_index: 0,
+                front: W::ZERO,
+                back_index: 0,
+                back: W::ZERO,
+            };
+        }
+        let first_word = start / W::BITS;
+        let last_word = (end - 1) / W::BITS;
+        let first_mask = !low_mask::<W>(start % W::BITS);
+        let last_mask = low_mask::<W>((end - 1) % W::BITS + 1);
+        if first_word == last_word {
+            Self {
+                front_index: first_word,
+                front: words[first_word] & first_mask & last_mask,
+                back_index: first_word,
+                back: W::ZERO,
+            }
+        } else {
+            Self {
+                front_index: first_word,
+                front: words[first_word] & first_mask,
+                back_index: last_word,
+                back: words[last_word] & last_mask,
+            }
+        }
+    }
+
+    /// An upper bound of the number of bits left to iterate over
+    fn upper_bound(&self) -> usize {
+        if self.front_index == self.back_index {
+            self.front.count_ones() as usize
+        } else {
+            (self.front.count_ones() + self.back.count_ones()) as usize
+                + (self.back_index - self.front_index - 1) * W::BITS
+        }
+    }
+
+    #[inline]
+    fn next(&mut self, words: &[W]) -> Option<usize> {
+        while self.front == W::ZERO {
+            if self.front_index == self.back_index {
+                return None;
+            }
+            self.front_index += 1;
+            if self.front_index == self.back_index {
+                self.front = self.back;
+                self.back = W::ZERO;
+            } else {
+                self.front = words[self.front_index];
+            }
+        }
+        let bit = self.front.trailing_zeros() as usize;
+        // clear the lowest set bit
+        self.front ^= W::ONE << bit;
+        Some(self.front_index * W::BITS + bit)
+    }
+
+    #[inline]
+    fn next_back(&mut self, words: &[W]) -> Option<usize> {
+        loop {
+            if self.front_index == self.back_index {
+                if self.front == W::ZERO {
+                    return None;
+                }
+                let bit = W::BITS - 1 - self.front.leading_zeros() as usize;
+                // clear the highest set bit
+                self.front ^= W::ONE << bit;
+                return Some(self.front_index * W::BITS + bit);
+            }
+            if self.back != W::ZERO {
+                let bit = W::BITS - 1 - self.back.leading_zeros() as usize;
+                self.back ^= W::ONE << bit;
+                return Some(self.back_index * W::BITS + bit);
+            }
+            self.back_index -= 1;
+            if self.back_index != self.front_index {
+                self.back = words[self.back_index];
+            }
+        }
+    }
+}
+
+/// Iterator over the bits set to true in the bit field container.
+/// Will iterate over the bits from lowest to to highest, or from highest to lowest when
+/// reversed.
+///
+/// The iterator keeps the current words in registers and pops the lowest (highest) set bit
+/// using `trailing_zeros()` (`leading_zeros()`), so the cost is O(number of set bits + number
+/// of words).
+#[derive(Clone)]
+pub struct YabfIterator<'s, W = u32> {
+    words: &'s [W],
+    cursor: Cursor<W>,
+}
+
+/// Iterator over the bits set to true in a [`SmallYabf`](crate::SmallYabf).
+#[cfg(feature = "smallvec")]
+pub type SmallYabfIterator<'s> = YabfIterator<'s>;
+
+impl<'s, W: Word> YabfIterator<'s, W> {
+    pub(crate) fn new(words: &'s [W]) -> Self {
+        Self::with_range(words, 0, usize::MAX)
+    }
+
+    /// Iterate over the set bits in `start..end`, `end` may be larger than the bit field.
+    pub(crate) fn with_range(words: &'s [W], start: usize, end: usize) -> Self {
+        Self {
+            words,
+            cursor: Cursor::new(words, start, end),
+        }
+    }
+}
+
+/// Owning iterator over the bits set to true, created by `yabf.into_iter()`.
+#[derive(Clone)]
+pub struct YabfIntoIterator<S: Storage> {
+    internals: S,
+    cursor: Cursor<S::Word>,
+}
+
+impl<S: Storage> GenericYabf<S> {
+    /// Returns an iterator over the set bits, same as `(&yabf).into_iter()`.
+    #[inline]
+    pub fn iter(&self) -> YabfIterator<'_, S::Word> {
+        YabfIterator::new(self.internals.as_slice())
+    }
+
+    /// Returns an iterator over the set bits within `range`. The iteration starts directly
+    /// at the first word of the range.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let mut bf = Yabf::default();
+    /// for i in [1, 40, 100, 1000].iter() {
+    ///     bf.set_bit(*i, true);
+    /// }
+    /// assert_eq!(bf.iter_range(40..1000).collect::<Vec<_>>(), vec![40, 100]);
+    /// assert_eq!(bf.iter_range(2..=1000).rev().collect::<Vec<_>>(), vec![1000, 100, 40]);
+    /// ```
+    #[inline]
+    pub fn iter_range<R: RangeBounds<usize>>(&self, range: R) -> YabfIterator<'_, S::Word> {
+        let range = to_range(&range);
+        YabfIterator::with_range(self.internals.as_slice(), range.start, range.end)
+    }
+
+    /// Returns an iterator over the set bits at or above bit `n`.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let mut bf = Yabf::default();
+    /// bf.set_bit(3, true);
+    /// bf.set_bit(300, true);
+    /// assert_eq!(bf.iter_from(4).collect::<Vec<_>>(), vec![300]);
+    /// ```
+    #[inline]
+    pub fn iter_from(&self, n: usize) -> YabfIterator<'_, S::Word> {
+        self.iter_range(n..)
+    }
+}
+
+impl<'a, S: Storage> IntoIterator for &'a GenericYabf<S> {
+    type Item = usize;
+    type IntoIter = YabfIterator<'a, S::Word>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        YabfIterator::new(self.internals.as_slice())
+    }
+}
+
+impl<'s, W: Word> Iterator for YabfIterator<'s, W> {
+    type Item = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        self.cursor.next(self.words)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.cursor.upper_bound()))
+    }
+}
+
+impl<'s, W: Word> DoubleEndedIterator for YabfIterator<'s, W> {
+    #[inline]
+    fn next_back(&mut self) -> Option<usize> {
+        self.cursor.next_back(self.words)
+    }
+}
+
+impl<'s, W: Word> FusedIterator for YabfIterator<'s, W> {}
+
+/// Consumes the bit field and iterates over the set bits.
+///
+/// ```
+/// # use yabf::Yabf;
+///
+/// let bf: Yabf = vec![5, 1, 3].into_iter().collect();
+/// assert_eq!(bf.into_iter().rev().collect::<Vec<_>>(), vec![5, 3, 1]);
+/// ```
+impl<S: Storage> IntoIterator for GenericYabf<S> {
+    type Item = usize;
+    type IntoIter = YabfIntoIterator<S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let cursor = Cursor::new(self.internals.as_slice(), 0, usize::MAX);
+        YabfIntoIterator {
+            internals: self.internals,
+            cursor,
+        }
+    }
+}
+
+impl<S: Storage> Iterator for YabfIntoIterator<S> {
+    type Item = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        self.cursor.next(self.internals.as_slice())
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.cursor.upper_bound()))
+    }
+}
+
+impl<S: Storage> DoubleEndedIterator for YabfIntoIterator<S> {
+    #[inline]
+    fn next_back(&mut self) -> Option<usize> {
+        self.cursor.next_back(self.internals.as_slice())
+    }
+}
+
+impl<S: Storage> FusedIterator for YabfIntoIterator<S> {}
+
+/// Builds a bit field with the given bits set, see [`Extend`] for how the storage grows.
+///
+/// ```
+/// # use yabf::Yabf;
+///
+/// let bf: Yabf = (0..100).step_by(10).collect();
+/// assert_eq!(bf.count_ones(), 10);
+/// assert!(bf.bit(90));
+/// ```
+impl<S: Storage> FromIterator<usize> for GenericYabf<S> {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut rv = Self::with_capacity(0);
+        rv.extend(iter);
+        rv
+    }
+}
+
+/// Sets the given bits. The indices are buffered first, so that the storage is grown only
+/// once, to the largest index.
+///
+/// ```
+/// # use yabf::Yabf;
+///
+/// let mut bf = Yabf::default();
+/// bf.extend(vec![1, 5]);
+/// bf.extend(&[7, 9]);
+/// assert_eq!(bf.into_iter().collect::<Vec<_>>(), vec![1, 5, 7, 9]);
+/// ```
+impl<S: Storage> Extend<usize> for GenericYabf<S> {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        let mut indices = Vec::with_capacity(iter.size_hint().0);
+        indices.extend(iter);
+        if let Some(max) = indices.iter().max() {
+            let words = max / S::Word::BITS + 1;
+            if words > self.internal_len() {
+                self.internals.resize(words);
+            }
+        }
+        for n in indices {
+            self.set_bit(n, true);
+        }
+    }
+}
+
+impl<'a, S: Storage> Extend<&'a usize> for GenericYabf<S> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = &'a usize>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied())
+    }
+}
+
+/// Iterator over the bits set to false within a range, created by
+/// [`GenericYabf::iter_zeros`]. Will iterate over the bits from lowest to highest.
+///
+/// Bits beyond the end of the internal storage are treated as zero.
+#[derive(Clone)]
+pub struct YabfZerosIterator<'s, W = u32> {
+    words: &'s [W],
+    // the index of the word held in `current`
+    index: usize,
+    // the not yet returned zero bits of the current word, inverted (i.e. set)
+    current: W,
+    // the index of the last word of the range
+    last_index: usize,
+    // the bits of the last word that are inside the range
+    last_mask: W,
+}
+
+impl<'s, W: Word> YabfZerosIterator<'s, W> {
+    fn with_range(words: &'s [W], start: usize, end: usize) -> Self {
+        if start >= end {
+            return Self {
+                words,
+                index: 0,
+                current: W::ZERO,
+                last_index: 0,
+                last_mask: W::ZERO,
+            };
+        }
+        let first_word = start / W::BITS;
+        let last_index = (end - 1) / W::BITS;
+        let last_mask = low_mask::<W>((end - 1) % W::BITS + 1);
+        let mut current = !Self::word(words, first_word) & !low_mask::<W>(start % W::BITS);
+        if first_word == last_index {
+            current &= last_mask;
+        }
+        Self {
+            words,
+            index: first_word,
+            current,
+            last_index,
+            last_mask,
+        }
+    }
+
+    #[inline]
+    fn word(words: &[W], index: usize) -> W {
+        words.get(index).copied().unwrap_or(W::ZERO)
+    }
+}
+
+impl<S: Storage> GenericYabf<S> {
+    /// Returns an iterator over the bits set to `false` within `range`. Everything beyond
+    /// `internal_len()` is treated as zero, so an unbounded range end will iterate all the
+    /// way up to `usize::MAX`.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let mut bf = Yabf::default();
+    /// bf.set_bit(1, true);
+    /// bf.set_bit(3, true);
+    /// assert_eq!(bf.iter_zeros(0..6).collect::<Vec<_>>(), vec![0, 2, 4, 5]);
+    /// assert_eq!(bf.iter_zeros(..).nth(100), Some(102));
+    /// ```
+    #[inline]
+    pub fn iter_zeros<R: RangeBounds<usize>>(&self, range: R) -> YabfZerosIterator<'_, S::Word> {
+        let range = to_range(&range);
+        YabfZerosIterator::with_range(self.internals.as_slice(), range.start, range.end)
+    }
+}
+
+impl<'s, W: Word> Iterator for YabfZerosIterator<'s, W> {
+    type Item = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        while self.current == W::ZERO {
+            if self.index >= self.last_index {
+                return None;
+            }
+            self.index += 1;
+            self.current = !Self::word(self.words, self.index);
+            if self.index == self.last_index {
+                self.current &= self.last_mask;
+            }
+        }
+        let bit = self.current.trailing_zeros() as usize;
+        self.current ^= W::ONE << bit;
+        Some(self.index * W::BITS + bit)
+    }
+}
+
+impl<'s, W: Word> FusedIterator for YabfZerosIterator<'s, W> {}
+
+/// Iterator over the bits set to true with a known length, created by
+/// [`RankSelect::iter`] and [`RankSelect::iter_range`].
+#[derive(Clone)]
+pub struct ExactYabfIterator<'s, W = u32> {
+    inner: YabfIterator<'s, W>,
+    remaining: usize,
+}
+
+impl<'a, S: Storage> RankSelect<'a, S> {
+    /// Returns an iterator over the set bits that knows its length.
+    ///
+    /// ```
+    /// # use yabf::{RankSelect, Yabf};
+    ///
+    /// let mut bf = Yabf::default();
+    /// bf.set_bit(3, true);
+    /// bf.set_bit(300, true);
+    /// let index = RankSelect::new(&bf);
+    /// assert_eq!(index.iter().len(), 2);
+    /// assert_eq!(index.iter_range(4..).len(), 1);
+    /// ```
+    #[inline]
+    pub fn iter(&self) -> ExactYabfIterator<'a, S::Word> {
+        self.iter_range(..)
+    }
+
+    /// Returns an iterator over the set bits within `range` that knows its length.
+    pub fn iter_range<R: RangeBounds<usize>>(&self, range: R) -> ExactYabfIterator<'a, S::Word> {
+        let range = to_range(&range);
+        let remaining = if range.start < range.end {
+            self.rank(range.end) - self.rank(range.start)
+        } else {
+            0
+        };
+        ExactYabfIterator {
+            inner: YabfIterator::with_range(
+                self.yabf().internals.as_slice(),
+                range.start,
+                range.end,
+            ),
+            remaining,
+        }
+    }
+}
+
+impl<'s, W: Word> Iterator for ExactYabfIterator<'s, W> {
+    type Item = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        let rv = self.inner.next();
+        if rv.is_some() {
+            self.remaining -= 1;
+        }
+        rv
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'s, W: Word> DoubleEndedIterator for ExactYabfIterator<'s, W> {
+    #[inline]
+    fn next_back(&mut self) -> Option<usize> {
+        let rv = self.inner.next_back();
+        if rv.is_some() {
+            self.remaining -= 1;
+        }
+        rv
+    }
+}
+
+impl<'s, W: Word> ExactSizeIterator for ExactYabfIterator<'s, W> {}
+
+impl<'s, W: Word> FusedIterator for ExactYabfIterator<'s, W> {}
+
+#[cfg(test)]
+mod test {
+    use crate::GenericYabf;
+
+    const BITS: [usize; 14] = [0, 1, 7, 8, 31, 32, 33, 63, 64, 127, 128, 129, 500, 4095];
+
+    fn check<S: crate::Storage + Default>() {
+        let mut bf = GenericYabf::<S>::default();
+        assert_eq!(bf.iter().next(), None);
+        assert_eq!(bf.iter().next_back(), None);
+        for b in BITS.iter() {
+            bf.set_bit(*b, true);
+        }
+        assert_eq!(bf.iter().collect::<Vec<usize>>(), BITS.to_vec());
+        // trailing zero words
+        bf.set_bit(10000, true);
+        bf.set_bit(10000, false);
+        assert_eq!(bf.iter().collect::<Vec<usize>>(), BITS.to_vec());
+        let mut reversed = BITS.to_vec();
+        reversed.reverse();
+        assert_eq!(bf.iter().rev().collect::<Vec<usize>>(), reversed);
+
+        for start in 0..140 {
+            for end in (start..600).step_by(7) {
+                let expected: Vec<usize> = BITS
+                    .iter()
+                    .copied()
+                    .filter(|b| *b >= start && *b < end)
+                    .collect();
+                assert_eq!(bf.iter_range(start..end).collect::<Vec<_>>(), expected);
+                let mut rev = bf.iter_range(start..end).rev().collect::<Vec<_>>();
+                rev.reverse();
+                assert_eq!(rev, expected);
+                assert!(bf.iter_range(start..end).size_hint().1.unwrap() >= expected.len());
+            }
+            let expected: Vec<usize> = BITS.iter().copied().filter(|b| *b >= start).collect();
+            assert_eq!(bf.iter_from(start).collect::<Vec<_>>(), expected);
+        }
+    }
+
+    #[test]
+    fn test_iter_word_types() {
+        check::<Vec<u8>>();
+        check::<Vec<u16>>();
+        check::<Vec<u32>>();
+        check::<Vec<u64>>();
+        check::<Vec<u128>>();
+        #[cfg(feature = "smallvec")]
+        check::<smallvec::SmallVec<[u32; 4]>>();
+    }
+
+    #[test]
+    fn test_iter_all_set() {
+        let mut bf = crate::Yabf::default();
+        for i in 0..1000 {
+            bf.set_bit(i, true);
+        }
+        assert!(bf.iter().eq(0..1000));
+        assert!(bf.iter().rev().eq((0..1000).rev()));
+    }
+
+    #[test]
+    fn test_iter_both_ends() {
+        let mut bf = crate::Yabf::default();
+        for b in BITS.iter() {
+            bf.set_bit(*b, true);
+        }
+        // alternate between the ends, the two halves must meet without loss or duplicates
+        let mut it = bf.iter();
+        let mut seen = Vec::new();
+        loop {
+            match (it.next(), it.next_back()) {
+                (Some(a), Some(b)) => {
+                    seen.push(a);
+                    seen.push(b)
+                }
+                (Some(a), None) => seen.push(a),
+                (None, Some(b)) => seen.push(b),
+                (None, None) => break,
+            }
+        }
+        seen.sort_unstable();
+        assert_eq!(seen, BITS.to_vec());
+    }
+
+    fn check_zeros<S: crate::Storage + Default>() {
+        let mut bf = GenericYabf::<S>::default();
+        assert!(bf.iter_zeros(0..300).eq(0..300));
+        for b in BITS.iter() {
+            bf.set_bit(*b, true);
+        }
+        for start in 0..140 {
+            for end in (start..5000).step_by(331) {
+                assert!(
+                    bf.iter_zeros(start..end)
+                        .eq((start..end).filter(|b| !BITS.contains(b))),
+                    "{}..{}",
+                    start,
+                    end
+                );
+            }
+        }
+        assert_eq!(
+            bf.iter_zeros(4090..).take(8).collect::<Vec<_>>(),
+            vec![4090, 4091, 4092, 4093, 4094, 4096, 4097, 4098]
+        );
+        assert_eq!(bf.iter_zeros(usize::MAX - 2..).count(), 2);
+    }
+
+    #[test]
+    fn test_iter_zeros() {
+        check_zeros::<Vec<u8>>();
+        check_zeros::<Vec<u32>>();
+        check_zeros::<Vec<u128>>();
+        #[cfg(feature = "smallvec")]
+        check_zeros::<smallvec::SmallVec<[u32; 4]>>();
+    }
+
+    #[test]
+    fn test_collect_and_into_iter() {
+        let bf: crate::Yabf = BITS.iter().rev().copied().collect();
+        assert_eq!(bf.iter().collect::<Vec<_>>(), BITS.to_vec());
+        let mut other = crate::Yabf::default();
+        other.extend(BITS.iter());
+        assert_eq!(other, bf);
+        other.extend(vec![5000, 5001]);
+        assert_eq!(other.count_ones(), BITS.len() + 2);
+        // the storage is grown to the largest index, not to the number of indices
+        other.extend(vec![100_000, 7, 60_000]);
+        assert_eq!(other.internal_len(), 100_000 / 32 + 1);
+        assert_eq!(other.last_set(), Some(100_000));
+
+        let mut it = bf.into_iter();
+        assert_eq!(it.next(), Some(0));
+        assert_eq!(it.next_back(), Some(4095));
+        assert_eq!(it.collect::<Vec<_>>(), BITS[1..BITS.len() - 1].to_vec());
+    }
+
+    #[cfg(feature = "smallvec")]
+    #[test]
+    fn test_collect_small() {
+        let bf: crate::SmallYabf = BITS.iter().copied().collect();
+        assert!(bf.into_iter().eq(BITS.iter().copied()));
+    }
+
+    #[test]
+    fn test_exact_size() {
+        let mut bf = crate::Yabf::default();
+        for b in BITS.iter() {
+            bf.set_bit(*b, true);
+        }
+        let index = crate::RankSelect::new(&bf);
+        let mut it = index.iter();
+        assert_eq!(it.len(), BITS.len());
+        let _ = it.next();
+        let _ = it.next_back();
+        assert_eq!(it.len(), BITS.len() - 2);
+        assert_eq!(it.count(), BITS.len() - 2);
+        assert_eq!(index.iter_range(8..=64).len(), 6);
+        assert_eq!(index.iter_range(8..=64).collect::<Vec<_>>().len(), 6);
+    }
+}
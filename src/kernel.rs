@@ -0,0 +1,327 @@
+//! The text formats the Linux kernel uses for CPU and NUMA node sets in `/sys` and `/proc`.
+
+use crate::text::{parse_index, ParseError};
+use crate::{GenericYabf, Storage};
+use std::fmt::Write;
+
+impl<S: Storage> GenericYabf<S> {
+    /// Parses the kernel "cpulist" format, e.g. `0-3,8,10-15`. Each comma separated group is
+    /// either an index or an inclusive range `a-b`. A range may be followed by
+    /// `:used/group`, which selects the first `used` bits of every `group` bits, starting
+    /// at `a`. An empty string, or one holding only white space, is an empty list.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let bf = Yabf::parse_list("0-3,8,10-15\n").unwrap();
+    /// assert_eq!(bf.count_ones(), 11);
+    /// let bf = Yabf::parse_list("0-15:2/4").unwrap();
+    /// assert_eq!(bf.iter().collect::<Vec<_>>(), vec![0, 1, 4, 5, 8, 9, 12, 13]);
+    /// assert!(Yabf::parse_list("3-1").is_err());
+    /// ```
+    pub fn parse_list(s: &str) -> Result<Self, ParseError> {
+        let mut rv = Self::with_capacity(0);
+        if s.trim().is_empty() {
+            return Ok(rv);
+        }
+        let mut offset = 0;
+        for group in s.split(',') {
+            rv.parse_list_group(group, offset)?;
+            offset += group.len() + 1;
+        }
+        Ok(rv)
+    }
+
+    /// Parses and sets one group of the "cpulist" format, `offset` is the position of `group`
+    /// in the parsed string.
+    fn parse_list_group(&mut self, group: &str, offset: usize) -> Result<(), ParseError> {
+        let (range, stride) = match group.find(':') {
+            Some(i) => (&group[..i], Some((&group[i + 1..], offset + i + 1))),
+            None => (group, None),
+        };
+        let (start, (end, end_offset)) = match range.find('-') {
+            Some(i) => (
+                parse_index(&range[..i], offset)?,
+                (
+                    parse_index(&range[i + 1..], offset + i + 1)?,
+                    offset + i + 1,
+                ),
+            ),
+            None => {
+                let index = parse_index(range, offset)?;
+                (index, (index, offset))
+            }
+        };
+        if start > end {
+            return Err(ParseError::InvalidRange(
+                offset + group.len() - group.trim_start().len(),
+            ));
+        }
+        // the exclusive end, an inclusive end of usize::MAX can not be set
+        let end = end.checked_add(1).ok_or(ParseError::Overflow(end_offset))?;
+        let (stride, stride_offset) = match stride {
+            Some(stride) => stride,
+            None => {
+                self.set_range(start..end);
+                return Ok(());
+            }
+        };
+        let slash = stride.find('/').ok_or(ParseError::Unexpected {
+            position: stride_offset + stride.len(),
+            expected: "`/`",
+        })?;
+        let used = parse_index(&stride[..slash], stride_offset)?;
+        let size = parse_index(&stride[slash + 1..], stride_offset + slash + 1)?;
+        if used == 0 || used > size {
+            return Err(ParseError::InvalidRange(stride_offset));
+        }
+        let mut first = start;
+        while first < end {
+            self.set_range(first..first.saturating_add(used).min(end));
+            first = match first.checked_add(size) {
+                Some(first) => first,
+                None => break,
+            };
+        }
+        Ok(())
+    }
+
+    /// Returns the set bits in the kernel "cpulist" format, using inclusive ranges for runs
+    /// of two or more bits. This is the inverse of [`parse_list`].
+    ///
+    /// [`parse_list`]: GenericYabf::parse_list
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let bf = Yabf::from_ranges(vec![0..4, 8..9, 10..16]);
+    /// assert_eq!(bf.to_list_string(), "0-3,8,10-15");
+    /// assert_eq!(Yabf::default().to_list_string(), "");
+    /// ```
+    pub fn to_list_string(&self) -> String {
+        let mut rv = String::new();
+        for run in self.runs() {
+            if !rv.is_empty() {
+                rv.push(',');
+            }
+            if run.len() == 1 {
+                rv.push_str(&run.start.to_string());
+            } else {
+                rv.push_str(&format!("{}-{}", run.start, run.end - 1));
+            }
+        }
+        rv
+    }
+}
+
+impl<S: Storage<Word = u32>> GenericYabf<S> {
+    /// Parses the kernel hex mask format, e.g. `ff,ffffffff` as found in `/proc/<pid>/status`
+    /// and the `cpumap` files. Each comma separated chunk holds up to 8 hex digits and becomes
+    /// one word of the internal storage, the most significant chunk comes first. Leading zero
+    /// chunks are kept as zero words, so [`to_kernel_mask`] restores the same chunks.
+    ///
+    /// [`to_kernel_mask`]: GenericYabf::to_kernel_mask
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let bf = Yabf::parse_kernel_mask("00000000,00000100,00000003\n").unwrap();
+    /// assert_eq!(bf.iter().collect::<Vec<_>>(), vec![0, 1, 40]);
+    /// assert_eq!(bf.internal_len(), 3);
+    /// ```
+    pub fn parse_kernel_mask(s: &str) -> Result<Self, ParseError> {
+        let mut offset = s.len() - s.trim_start().len();
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(ParseError::Empty);
+        }
+        let count = s.split(',').count();
+        let mut internals = S::with_capacity(count);
+        internals.resize(count);
+        let words = internals.as_mut_slice();
+        for (i, chunk) in s.split(',').enumerate() {
+            if chunk.is_empty() {
+                return Err(ParseError::Unexpected {
+                    position: offset,
+                    expected: "a hex digit",
+                });
+            }
+            let word = &mut words[count - 1 - i];
+            for (j, c) in chunk.char_indices() {
+                if j == 8 {
+                    return Err(ParseError::Unexpected {
+                        position: offset + j,
+                        expected: "`,`",
+                    });
+                }
+                let v = c.to_digit(16).ok_or(ParseError::InvalidDigit(offset + j))?;
+                *word = *word << 4 | v;
+            }
+            offset += chunk.len() + 1;
+        }
+        Ok(Self { internals })
+    }
+
+    /// Returns the bits in the kernel hex mask format, one zero padded chunk of 8 hex digits
+    /// per word of the internal storage, the most significant first. An empty storage is
+    /// printed as a single zero chunk.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let mut bf = Yabf::default();
+    /// bf.set_bit(32, true);
+    /// assert_eq!(bf.to_kernel_mask(), "00000001,00000000");
+    /// assert_eq!(Yabf::default().to_kernel_mask(), "00000000");
+    /// ```
+    pub fn to_kernel_mask(&self) -> String {
+        let words = self.internals.as_slice();
+        if words.is_empty() {
+            return "00000000".to_string();
+        }
+        let mut rv = String::with_capacity(words.len() * 9);
+        for (n, w) in words.iter().rev().enumerate() {
+            if n > 0 {
+                rv.push(',');
+            }
+            let _ = write!(rv, "{:08x}", w);
+        }
+        rv
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{GenericYabf, ParseError, Yabf};
+
+    fn check<S: crate::Storage + Default>() {
+        for list in ["", "0", "0-3,8,10-15", "1,3,5-6,31-32,63-64,127-200,1000"].iter() {
+            let bf = GenericYabf::<S>::parse_list(list).unwrap();
+            assert_eq!(&bf.to_list_string(), list);
+        }
+        let bf = GenericYabf::<S>::parse_list("64-69:1/2,100,3-3,2").unwrap();
+        assert_eq!(bf.to_list_string(), "2-3,64,66,68,100");
+    }
+
+    #[test]
+    fn test_list() {
+        check::<Vec<u8>>();
+        check::<Vec<u32>>();
+        check::<Vec<u128>>();
+        #[cfg(feature = "smallvec")]
+        check::<smallvec::SmallVec<[u32; 4]>>();
+    }
+
+    #[test]
+    fn test_stride() {
+        let bf = Yabf::parse_list("0-10:3/4").unwrap();
+        assert_eq!(bf.to_list_string(), "0-2,4-6,8-10");
+        let bf = Yabf::parse_list("5-9:1/1").unwrap();
+        assert_eq!(bf.to_list_string(), "5-9");
+        let bf = Yabf::parse_list("5-20:2/100").unwrap();
+        assert_eq!(bf.to_list_string(), "5-6");
+        let bf = Yabf::parse_list(" 2-5:1/2 , 9 \n").unwrap();
+        assert_eq!(bf.to_list_string(), "2,4,9");
+    }
+
+    #[test]
+    fn test_kernel_mask() {
+        for mask in [
+            "00000000",
+            "00000001",
+            "ffffffff,ffffffff",
+            "00000000,00000000,80000000,00000001",
+        ]
+        .iter()
+        {
+            let bf = Yabf::parse_kernel_mask(mask).unwrap();
+            assert_eq!(&bf.to_kernel_mask(), mask);
+        }
+        let bf = Yabf::parse_kernel_mask("ff,ffffffff").unwrap();
+        assert_eq!(bf.to_list_string(), "0-39");
+        assert_eq!(bf.to_kernel_mask(), "000000ff,ffffffff");
+        let bf = Yabf::parse_kernel_mask("0,1,F").unwrap();
+        assert_eq!(bf.to_list_string(), "0-3,32");
+        assert_eq!(bf.internal_len(), 3);
+        #[cfg(feature = "smallvec")]
+        {
+            let small = crate::SmallYabf::parse_kernel_mask("ff,ffffffff").unwrap();
+            assert_eq!(small.to_kernel_mask(), "000000ff,ffffffff");
+        }
+
+        assert_eq!(Yabf::parse_kernel_mask(" \n"), Err(ParseError::Empty));
+        assert_eq!(
+            Yabf::parse_kernel_mask("ff,,1"),
+            Err(ParseError::Unexpected {
+                position: 3,
+                expected: "a hex digit"
+            })
+        );
+        assert_eq!(
+            Yabf::parse_kernel_mask("1,123456789"),
+            Err(ParseError::Unexpected {
+                position: 10,
+                expected: "`,`"
+            })
+        );
+        assert_eq!(
+            Yabf::parse_kernel_mask("ff,0000000g"),
+            Err(ParseError::InvalidDigit(10))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Yabf::parse_list("1,5-3"), Err(ParseError::InvalidRange(2)));
+        assert_eq!(
+            Yabf::parse_list("0-7:0/4"),
+            Err(ParseError::InvalidRange(4))
+        );
+        assert_eq!(
+            Yabf::parse_list("0-7:5/4"),
+            Err(ParseError::InvalidRange(4))
+        );
+        assert_eq!(
+            Yabf::parse_list("0-7:2"),
+            Err(ParseError::Unexpected {
+                position: 5,
+                expected: "`/`"
+            })
+        );
+        assert_eq!(
+            Yabf::parse_list("1,,2"),
+            Err(ParseError::Unexpected {
+                position: 2,
+                expected: "an index"
+            })
+        );
+        assert_eq!(
+            Yabf::parse_list("1-"),
+            Err(ParseError::Unexpected {
+                position: 2,
+                expected: "an index"
+            })
+        );
+        assert_eq!(Yabf::parse_list("0-x"), Err(ParseError::InvalidDigit(2)));
+        assert_eq!(Yabf::parse_list("1-2-3"), Err(ParseError::InvalidDigit(3)));
+        assert_eq!(
+            Yabf::parse_list("99999999999999999999999"),
+            Err(ParseError::Overflow(0))
+        );
+        // an inclusive end of usize::MAX does not fit in a range
+        let max = usize::MAX;
+        assert_eq!(
+            Yabf::parse_list(&max.to_string()),
+            Err(ParseError::Overflow(0))
+        );
+        assert_eq!(
+            Yabf::parse_list(&format!("1,0-{}", max)),
+            Err(ParseError::Overflow(4))
+        );
+        let group = format!("{}-{}:1000/1001", max - 1000, max);
+        assert_eq!(
+            Yabf::parse_list(&group),
+            Err(ParseError::Overflow(group.find('-').unwrap() + 1))
+        );
+    }
+}
@@ -39,13 +39,17 @@
 //! # Yabf
 //! Just what the world needed - yet another bit field struct.
 //!
-//! This is a small and simple implementation. It only has the basic functionality of a bit field:
-//!  * Set arbitary bit (if you set the millionth bit the list will use at least 125KB of heap space)
+//! `Yabf` and `SmallYabf` store the bits in plain (non-sparse) vectors:
+//!  * Set arbitrary bit (if you set the millionth bit the list will use at least 125KB of heap space)
 //!  * Get bit value
-//!  * An iterator over the set bit indices. O(size of container)
-//!  * The container never shrinks.
+//!  * An iterator over the set bit indices. O(number of set bits + size of container)
+//!  * The container only shrinks on request, see `shrink_to_fit()`, `truncate()` and `AutoTrim`.
+//!  * Optional serde support, enabled by the `serde` feature.
 //!
-//! The bits are stored in plain (non-sparse) arrays/vectors.
+//! For sparse, clustered or huge indices there are three more bit fields:
+//!  * `RoaringYabf`, a compressed bit field for sparse or clustered indices.
+//!  * `EwahYabf`, a run length encoded bit field for long runs of set or cleared bits.
+//!  * `SparseYabf`, a bit field for huge, scattered indices.
 //!
 //! ```{rust}
 //! use yabf::Yabf;
@@ -79,8 +83,42 @@
 #![deny(unused_imports)]
 #![allow(unused_imports)]
 
+mod bitops;
+mod cmp;
+mod convert;
+mod ewah;
+mod iter;
+mod kernel;
+mod persist;
+mod range;
+mod rank;
+mod roaring;
+mod runs;
+mod search;
+#[cfg(feature = "serde")]
+mod serialize;
+mod similarity;
+mod sparse;
+mod storage;
+mod text;
+mod word;
+
+pub use ewah::{EwahIterator, EwahYabf};
+#[cfg(feature = "smallvec")]
+pub use iter::SmallYabfIterator;
+pub use iter::{ExactYabfIterator, YabfIntoIterator, YabfIterator, YabfZerosIterator};
+pub use persist::{Encoding, ReadError, MAX_BIT_LEN};
+pub use rank::RankSelect;
+pub use roaring::{RoaringIterator, RoaringYabf};
+pub use runs::YabfRunsIterator;
+pub use similarity::Similarity;
+pub use sparse::{SparseIterator, SparseYabf};
+pub use storage::{AutoTrim, Storage};
+pub use text::ParseError;
+pub use word::Word;
+
 use core::fmt;
-use std::ops;
+use std::ops::{Bound, Range, RangeBounds};
 
 #[derive(Clone, Default)]
 /// Yet another bit field implementation.
@@ -89,14 +127,41 @@ use std::ops;
 /// It is intended for cases where a program iterates over list or other usize indexed containers
 /// and simple bit based bookkeeping is required.
 ///
-pub struct Yabf {
-    internals: Vec<u32>,
+/// The words are kept in a [`Storage`], normally you would use one of the type aliases
+/// [`Yabf`] or [`SmallYabf`] instead of naming this type directly. Those aliases use `u32`
+/// words, other word types can be selected by naming the storage explicitly:
+///
+/// ```
+/// # use yabf::GenericYabf;
+///
+/// let mut bf = GenericYabf::<Vec<u64>>::default();
+/// bf.set_bit(100, true);
+/// assert!(bf.bit(100));
+/// assert_eq!(bf.internal_len(), 2);
+/// ```
+pub struct GenericYabf<S> {
+    internals: S,
 }
 
-impl Yabf {
+/// A `std::vec::Vec` based bit field.
+pub type Yabf = GenericYabf<Vec<u32>>;
+
+/// A `smallvec::SmallVec` based bit field. The first 128 bits will be stored on the stack.
+#[cfg(feature = "smallvec")]
+pub type SmallYabf = GenericYabf<smallvec::SmallVec<[u32; 4]>>;
+
+/// A [`SmallYabf`] that trims itself when bits are cleared, falling back to inline storage
+/// when the set bits fit into the first 128 bits again. See [`AutoTrim`].
+#[cfg(feature = "smallvec")]
+pub type AutoTrimSmallYabf = GenericYabf<AutoTrim<smallvec::SmallVec<[u32; 4]>>>;
+
+impl<S: Storage> GenericYabf<S> {
     /// Construct an empty bit field with enough capacity pre-allocated to store at least `n`
     /// bits.
     ///
+    /// A `SmallYabf` will create a heap allocation only if `n` is larger than the inline
+    /// capacity of the internal SmallVec.
+    ///
     /// ```
     /// # use yabf::Yabf;
     ///
@@ -107,10 +172,29 @@ impl Yabf {
     /// ```
     pub fn with_capacity(bits: usize) -> Self {
         Self {
-            internals: Vec::<u32>::with_capacity((bits / 32) + 1),
+            internals: S::with_capacity((bits / S::Word::BITS) + 1),
         }
     }
 
+    /// Construct a bit field on top of an existing storage. The words already present in the
+    /// storage are used as is.
+    #[inline]
+    pub fn from_storage(storage: S) -> Self {
+        Self { internals: storage }
+    }
+
+    /// Returns a reference to the underlying storage
+    #[inline]
+    pub fn storage(&self) -> &S {
+        &self.internals
+    }
+
+    /// Consumes the bit field and returns the underlying storage
+    #[inline]
+    pub fn into_storage(self) -> S {
+        self.internals
+    }
+
     /// Returns the value of the 'n':th bit in the bit field.
     ///
     /// ```
@@ -123,18 +207,16 @@ impl Yabf {
     /// bf.set_bit(10,true);
     /// assert!(bf.bit(10));
     /// ```
+    #[inline]
     pub fn bit(&self, n: usize) -> bool {
-        let word = n / 32;
-        if word < self.internals.len() {
-            if let Some(value) = self.internals.get(word) {
-                return value & (1u32 << (n % 32)) != 0;
-            }
+        if let Some(value) = self.internals.as_slice().get(n / S::Word::BITS) {
+            return *value & (S::Word::ONE << (n % S::Word::BITS)) != S::Word::ZERO;
         }
         false
     }
 
     /// Sets the 'n':th bit in the bit field. If the bit field capacity is not large enough
-    /// more space will be allocated.
+    /// more space will be allocated. Clearing a bit outside of the allocated space is a no-op.
     ///
     /// ```
     /// # use yabf::Yabf;
@@ -147,57 +229,42 @@ impl Yabf {
     /// assert!(bf.bit(10));
     /// ```
     pub fn set_bit(&mut self, n: usize, state: bool) {
-        let word = n / 32;
+        let word = n / S::Word::BITS;
+        let bit_mask = S::Word::ONE << (n % S::Word::BITS);
 
-        if word >= self.internals.capacity() {
-            self.internals
-                .reserve_exact(1 + word - self.internals.capacity());
-        }
-
-        if self.internals.is_empty() {
-            // prime the array so that the math works
-            self.internals.push(0);
-        }
-        let bit_mask = 1_u32 << (n % 32);
-
-        if word >= self.internals.len() {
-            let old_word = self.internals.len() - 1;
-            for _i in old_word..word - 1 {
-                self.internals.push(0);
+        if word >= self.internals.as_slice().len() {
+            if !state {
+                return;
             }
-            if state {
-                self.internals.push(bit_mask);
-            } else {
-                self.internals.push(0);
-            }
-        } else if state {
-            self.internals[word] |= bit_mask;
+            self.internals.resize(word + 1);
+        }
+        if state {
+            self.internals.as_mut_slice()[word] |= bit_mask;
         } else {
-            self.internals[word] &= !bit_mask;
+            self.internals.as_mut_slice()[word] &= !bit_mask;
+            self.internals.bits_cleared();
         }
     }
 
     /// Returns `true` if all bits are set to `false`
     #[inline]
     pub fn is_empty(&self) -> bool {
-        for e in self.internals.iter() {
-            if *e != 0 {
-                return false;
-            }
-        }
-        true
+        self.internals
+            .as_slice()
+            .iter()
+            .all(|e| *e == S::Word::ZERO)
     }
 
     /// The number of bits the bit field can hold without reallocating
     #[inline]
     pub fn capacity(&self) -> usize {
-        self.internals.capacity() * 32
+        self.internals.capacity() * S::Word::BITS
     }
 
     /// The len() of the internal vector
     #[inline]
     pub fn internal_len(&self) -> usize {
-        self.internals.len()
+        self.internals.as_slice().len()
     }
 
     /// Reserve capacity for `additional_bits` more bits to be inserted.
@@ -207,464 +274,126 @@ impl Yabf {
     /// Panics if the capacity computation overflows `usize`.
     #[inline]
     pub fn reserve(&mut self, additional_bits: usize) {
-        let additional = additional_bits / 32;
+        let additional = additional_bits / S::Word::BITS;
         let additional = if additional < 1 { 1 } else { additional };
         self.internals.reserve(additional);
     }
 
     /// Remove all elements from the vector.
-    /// This method simply delegates clear() to the underlying vector
-    /// std::vec::Vec or smallvec::SmallVec. So what actually happen depends on the
-    /// feature set.
+    /// This method simply delegates clear() to the underlying storage,
+    /// e.g. std::vec::Vec or smallvec::SmallVec. So what actually happen depends on the
+    /// storage type.
     #[inline]
     pub fn clear(&mut self) {
         self.internals.clear();
     }
-}
-
-/// Iterator over the bits set to true in the bit field container.
-/// Will iterate over the bits from lowest to to highest.
-/// This is a relatively expensive O(size of container) operation.
-#[derive(Clone)]
-pub struct YabfIterator<'s> {
-    yabf: &'s Yabf,
-    last_word: usize,
-    // when this field is usize::MAX it means that the value was not
-    // actually the 'last' value yet, but rather that the bit 0 should be tested.
-    last_bit: usize,
-}
-
-impl<'s> YabfIterator<'s> {
-    pub(crate) fn new(yabf: &'s Yabf) -> Self {
-        Self {
-            yabf,
-            last_word: 0,
-            last_bit: usize::MAX,
-        }
-    }
-}
-
-impl<'a> IntoIterator for &'a Yabf {
-    type Item = usize;
-    type IntoIter = YabfIterator<'a>;
-
-    fn into_iter(self) -> Self::IntoIter {
-        YabfIterator::new(self)
-    }
-}
-
-impl<'s> Iterator for YabfIterator<'s> {
-    type Item = usize;
-
-    /// Maybe not the most efficient iterator possible, it iterates over each bit and tests
-    /// if it is set and return the corresponding bit number.
-    /// It skips to next word if the word bits (32 bits) are all zero, or all upper or lower
-    /// 16 bits are zero
-    fn next(&mut self) -> Option<usize> {
-        let mut next_word = self.last_word;
-
-        let mut next_bit = if self.last_bit == usize::MAX {
-            0
-        } else {
-            self.last_bit + 1
-        };
-
-        loop {
-            if next_bit > 31 {
-                next_bit = 0;
-                next_word += 1;
-                if next_word >= self.yabf.internals.len() {
-                    return None;
-                }
-            }
-            let sample = self.yabf.internals[next_word];
-            // Skip if all bits are zero
-            if sample == 0 {
-                next_word += 1;
-                if next_word >= self.yabf.internals.len() {
-                    return None;
-                }
-                next_bit = 0;
-                continue;
-            }
-            // Skip if the lower 16 bits are all zero
-            if next_bit < 16 && sample & 0xFFFF == 0 {
-                next_bit = 16;
-            }
-            // Skip if the high 16 bits are all zero
-            if next_bit >= 16 && sample & 0xFFFF0000 == 0 {
-                next_word += 1;
-                if next_word >= self.yabf.internals.len() {
-                    return None;
-                }
-                next_bit = 0;
-                continue;
-            }
-
-            //println!("Sample:{:?} word:{}, bit:{}", sample, next_word, next_bit);
-            while next_bit < 32 {
-                if sample & (1u32 << next_bit) != 0 {
-                    self.last_bit = next_bit;
-                    self.last_word = next_word;
-                    return Some(next_word * 32 + (next_bit as usize));
-                }
-                next_bit += 1;
-            }
-        }
-    }
-}
-
-impl fmt::Debug for Yabf {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if self.internals.is_empty() {
-            write!(f, "Yabf:0x0")
-        } else {
-            write!(f, "Yabf:0x")?;
-            for i in self.internals.iter().rev() {
-                write!(f, "{:08X}_", *i)?;
-            }
-            Ok(())
-        }
-    }
-}
-
-/// bit or assign operation.
-/// This is a relatively expensive O(size of container) operation.
-/// ```
-/// # use yabf::Yabf;
-///
-/// let mut a = Yabf::default();
-/// let mut b = Yabf::default();
-/// a.set_bit(45,true);
-/// b.set_bit(12345,true);
-/// assert!(!a.bit(12345));
-/// assert!(a.bit(45));
-/// a |= &b;
-/// assert!(a.bit(12345));
-/// assert!(a.bit(45));
-/// ```
-impl ops::BitOrAssign<&Yabf> for Yabf {
-    fn bitor_assign(&mut self, other: &Yabf) {
-        if self.internals.len() < other.internals.len() {
-            for v in other
-                .internals
-                .iter()
-                .enumerate()
-                .take(self.internals.len())
-            {
-                self.internals[v.0] |= v.1;
-            }
-            if other.internals.len() > self.internals.capacity() {
-                self.internals
-                    .reserve_exact(other.internals.len() - self.internals.capacity());
-            }
-            for v in other.internals.iter().skip(self.internals.len()) {
-                self.internals.push(*v);
-            }
-        } else {
-            for v in other.internals.iter().enumerate() {
-                self.internals[v.0] |= v.1;
-            }
-        }
-    }
-}
-
-#[derive(Clone, Default)]
-/// Yet another bit field implementation.
-/// This is a simple, small and hopefully efficient bit field implementation. It uses SmallVec
-/// as an internal container. The first 128 bits will be stored on the stack.
-///
-/// It is intended for cases where a program iterates over list or other usize indexed containers
-/// and simple bit based bookkeeping is required.
-///
-#[cfg(feature = "smallvec")]
-pub struct SmallYabf {
-    internals: smallvec::SmallVec<[u32; 4]>,
-}
 
-#[cfg(feature = "smallvec")]
-impl SmallYabf {
-    /// Construct an empty bit field with enough capacity pre-allocated to store at least `n`
-    /// bits.
-    ///
-    /// Will create a heap allocation only if `n` is larger than the inline capacity of the
-    /// internal SmallVec.
+    /// Drops the trailing all-zero words of the internal storage. The capacity is kept.
     ///
     /// ```
-    /// # use yabf::SmallYabf;
-    ///
-    /// let bf = SmallYabf::with_capacity(100);
+    /// # use yabf::Yabf;
     ///
-    /// assert!(bf.is_empty());
-    /// assert!(bf.capacity() >= 100);
+    /// let mut bf = Yabf::default();
+    /// bf.set_bit(1000, true);
+    /// bf.set_bit(1000, false);
+    /// assert_eq!(bf.internal_len(), 32);
+    /// bf.trim();
+    /// assert_eq!(bf.internal_len(), 0);
     /// ```
-    pub fn with_capacity(bits: usize) -> Self {
-        Self {
-            internals: smallvec::SmallVec::<[u32; 4]>::with_capacity((bits / 32) + 1),
+    #[inline]
+    pub fn trim(&mut self) {
+        let words = self.internals.as_slice();
+        let zeros = storage::trailing_zero_words(words);
+        if zeros > 0 {
+            let len = words.len() - zeros;
+            self.internals.resize(len);
         }
     }
 
-    /// Returns the value of the 'n':th bit in the bit field.
+    /// Drops the trailing all-zero words and releases the unused capacity of the internal
+    /// storage. A `SmallYabf` will move back to inline storage if the remaining words fit.
     ///
     /// ```
-    /// # use yabf::SmallYabf;
-    ///
-    /// let mut bf = SmallYabf::default();
+    /// # use yabf::Yabf;
     ///
-    /// assert!(bf.is_empty());
-    /// assert!(!bf.bit(10));
-    /// bf.set_bit(10,true);
-    /// assert!(bf.bit(10));
+    /// let mut bf = Yabf::default();
+    /// bf.set_bit(10_000_000, true);
+    /// bf.set_bit(10_000_000, false);
+    /// bf.set_bit(3, true);
+    /// bf.shrink_to_fit();
+    /// assert_eq!(bf.internal_len(), 1);
+    /// assert!(bf.capacity() < 1000);
     /// ```
-    pub fn bit(&self, n: usize) -> bool {
-        let word = n / 32;
-        if word < self.internals.len() {
-            if let Some(value) = self.internals.get(word) {
-                return value & (1u32 << (n % 32)) != 0;
-            }
-        }
-        false
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.trim();
+        self.internals.shrink_to_fit();
     }
 
-    /// Sets the 'n':th bit in the bit field. If the bit field capacity is not large enough
-    /// more space will be allocated.
+    /// Clears every bit at or above bit `n` and releases the storage used by them.
     ///
     /// ```
-    /// # use yabf::SmallYabf;
-    ///
-    /// let mut bf = SmallYabf::default();
+    /// # use yabf::Yabf;
     ///
-    /// assert!(bf.is_empty());
-    /// assert!(!bf.bit(10));
-    /// bf.set_bit(10,true);
-    /// assert!(bf.bit(10));
+    /// let mut bf = Yabf::default();
+    /// bf.set_bit(3, true);
+    /// bf.set_bit(40, true);
+    /// bf.set_bit(10_000, true);
+    /// bf.truncate(40);
+    /// assert_eq!(bf.iter().collect::<Vec<_>>(), vec![3]);
+    /// assert_eq!(bf.internal_len(), 1);
     /// ```
-    pub fn set_bit(&mut self, n: usize, state: bool) {
-        let word = n / 32;
-
-        if self.internals.is_empty() {
-            // prime the array so that the math works
-            self.internals.push(0);
-        }
-        let bit_mask = 1_u32 << (n % 32);
-
-        if word >= self.internals.len() {
-            self.internals.reserve(1 + word - self.internals.len());
-            let old_word = self.internals.len() - 1;
-            for _i in old_word..word - 1 {
-                self.internals.push(0);
-            }
-            if state {
-                self.internals.push(bit_mask);
+    pub fn truncate(&mut self, n: usize) {
+        let words = n / S::Word::BITS;
+        if words < self.internal_len() {
+            let partial = n % S::Word::BITS;
+            if partial == 0 {
+                self.internals.resize(words);
             } else {
-                self.internals.push(0);
-            }
-        } else if state {
-            self.internals[word] |= bit_mask;
-        } else {
-            self.internals[word] &= !bit_mask;
-        }
-    }
-
-    /// Returns `true` if all bits are set to `false`
-    #[inline]
-    pub fn is_empty(&self) -> bool {
-        for e in self.internals.iter() {
-            if *e != 0 {
-                return false;
+                self.internals.resize(words + 1);
+                self.internals.as_mut_slice()[words] &= word::low_mask::<S::Word>(partial);
             }
         }
-        true
-    }
-
-    /// The number of bits the bit field can hold without reallocating
-    #[inline]
-    pub fn capacity(&self) -> usize {
-        self.internals.capacity() * 32
-    }
-
-    /// The len() of the internal vector
-    #[inline]
-    pub fn internal_len(&self) -> usize {
-        self.internals.len()
-    }
-
-    /// Reserve capacity for `additional_bits` more bits to be inserted.
-    ///
-    /// May reserve more space to avoid frequent re-allocations.
-    ///
-    /// Panics if the capacity computation overflows `usize`.
-    #[inline]
-    pub fn reserve(&mut self, additional_bits: usize) {
-        let additional = additional_bits / 32;
-        let additional = if additional < 1 { 1 } else { additional };
-        self.internals.reserve(additional);
-    }
-
-    /// Remove all elements from the vector.
-    /// This method simply delegates clear() to the underlying vector
-    /// std::vec::Vec or smallvec::SmallVec. So what actually happen depends on the
-    /// feature set.
-    #[inline]
-    pub fn clear(&mut self) {
-        self.internals.clear();
-    }
-}
-
-#[cfg(feature = "smallvec")]
-/// Iterator over the bits set to true in the bit field container.
-/// Will iterate over the bits from lowest to to highest.
-/// This is a relatively expensive O(size of container) operation.
-#[derive(Clone)]
-pub struct SmallYabfIterator<'s> {
-    yabf: &'s SmallYabf,
-    last_word: usize,
-    // when this field is usize::MAX it means that the value was not
-    // actually the 'last' value yet, but rather that the bit 0 should be tested.
-    last_bit: usize,
-}
-
-#[cfg(feature = "smallvec")]
-impl<'s> SmallYabfIterator<'s> {
-    pub(crate) fn new(yabf: &'s SmallYabf) -> Self {
-        Self {
-            yabf,
-            last_word: 0,
-            last_bit: usize::MAX,
-        }
+        self.shrink_to_fit();
     }
 }
 
-#[cfg(feature = "smallvec")]
-impl<'a> IntoIterator for &'a SmallYabf {
-    type Item = usize;
-    type IntoIter = SmallYabfIterator<'a>;
-
-    fn into_iter(self) -> Self::IntoIter {
-        SmallYabfIterator::new(self)
-    }
-}
-
-#[cfg(feature = "smallvec")]
-impl<'s> Iterator for SmallYabfIterator<'s> {
-    type Item = usize;
-
-    /// Maybe not the most efficient iterator possible, it iterates over each bit and tests
-    /// if it is set and return the corresponding bit number.
-    /// It skips to next word if the word bits (32 bits) are all zero, or all upper or lower
-    /// 16 bits are zero
-    fn next(&mut self) -> Option<usize> {
-        let mut next_word = self.last_word;
-
-        let mut next_bit = if self.last_bit == usize::MAX {
-            0
-        } else {
-            self.last_bit + 1
-        };
-
-        loop {
-            if next_bit > 31 {
-                next_bit = 0;
-                next_word += 1;
-                if next_word >= self.yabf.internals.len() {
-                    return None;
-                }
-            }
-            let sample = self.yabf.internals[next_word];
-            // Skip if all bits are zero
-            if sample == 0 {
-                next_word += 1;
-                if next_word >= self.yabf.internals.len() {
-                    return None;
-                }
-                next_bit = 0;
-                continue;
-            }
-            // Skip if the lower 16 bits are all zero
-            if next_bit < 16 && sample & 0xFFFF == 0 {
-                next_bit = 16;
-            }
-            // Skip if the high 16 bits are all zero
-            if next_bit >= 16 && sample & 0xFFFF0000 == 0 {
-                next_word += 1;
-                if next_word >= self.yabf.internals.len() {
-                    return None;
-                }
-                next_bit = 0;
-                continue;
-            }
-
-            //println!("Sample:{:?} word:{}, bit:{}", sample, next_word, next_bit);
-            while next_bit < 32 {
-                if sample & (1u32 << next_bit) != 0 {
-                    self.last_bit = next_bit;
-                    self.last_word = next_word;
-                    return Some(next_word * 32 + (next_bit as usize));
-                }
-                next_bit += 1;
-            }
-        }
-    }
+/// Converts any `RangeBounds<usize>` into a half open `Range`. An unbounded end becomes
+/// `usize::MAX`, callers are expected to clamp it to whatever limit makes sense.
+pub(crate) fn to_range<R: RangeBounds<usize>>(range: &R) -> Range<usize> {
+    let start = match range.start_bound() {
+        Bound::Included(s) => *s,
+        Bound::Excluded(s) => s.saturating_add(1),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(e) => e.saturating_add(1),
+        Bound::Excluded(e) => *e,
+        Bound::Unbounded => usize::MAX,
+    };
+    start..end
 }
 
-#[cfg(feature = "smallvec")]
-impl fmt::Debug for SmallYabf {
+impl<S: Storage> fmt::Debug for GenericYabf<S> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if self.internals.is_empty() {
-            write!(f, "SmallYabf:0x0")
+        let words = self.internals.as_slice();
+        if words.is_empty() {
+            write!(f, "{}:0x0", S::NAME)
         } else {
-            write!(f, "SmallYabf:0x")?;
-            for i in self.internals.iter().rev() {
-                write!(f, "{:08X}_", *i)?;
+            write!(f, "{}:0x", S::NAME)?;
+            for (n, i) in words.iter().rev().enumerate() {
+                if n > 0 {
+                    f.write_str("_")?;
+                }
+                write!(f, "{:01$X}", *i, S::Word::BITS / 4)?;
             }
             Ok(())
         }
     }
 }
 
-#[cfg(feature = "smallvec")]
-/// bit or assign operation
-/// This is a relatively expensive O(size of container) operation.
-/// ```
-/// # use yabf::SmallYabf;
-///
-/// let mut a = SmallYabf::default();
-/// let mut b = SmallYabf::default();
-/// a.set_bit(45,true);
-/// b.set_bit(12345,true);
-/// assert!(!a.bit(12345));
-/// assert!(a.bit(45));
-/// a |= &b;
-/// assert!(a.bit(12345));
-/// assert!(a.bit(45));
-/// ```
-impl ops::BitOrAssign<&SmallYabf> for SmallYabf {
-    fn bitor_assign(&mut self, other: &SmallYabf) {
-        if self.internals.len() < other.internals.len() {
-            for v in other
-                .internals
-                .iter()
-                .enumerate()
-                .take(self.internals.len())
-            {
-                self.internals[v.0] |= v.1;
-            }
-            if other.internals.len() > self.internals.capacity() {
-                self.internals
-                    .reserve_exact(other.internals.len() - self.internals.capacity());
-            }
-            for v in other.internals.iter().skip(self.internals.len()) {
-                self.internals.push(*v);
-            }
-        } else {
-            for v in other.internals.iter().enumerate() {
-                self.internals[v.0] |= v.1;
-            }
-        }
-    }
-}
-
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod test {
 
     #[test]
@@ -687,11 +416,11 @@ mod test {
     fn test_iter() {
         let mut bf = crate::Yabf::default();
         bf.set_bit(129, true);
-        println!("{:?}", bf.into_iter().collect::<Vec<usize>>());
-        assert_eq!(bf.into_iter().next().unwrap(), 129);
+        println!("{:?}", bf.iter().collect::<Vec<usize>>());
+        assert_eq!(bf.iter().next().unwrap(), 129);
         bf.set_bit(29, true);
         bf.set_bit(167, true);
-        println!("{:?}", bf.into_iter().collect::<Vec<usize>>());
+        println!("{:?}", bf.iter().collect::<Vec<usize>>());
     }
 
     #[test]
@@ -722,6 +451,69 @@ mod test {
         assert!(a.bit(4444));
     }
 
+    #[test]
+    fn test_word_types() {
+        fn check<S: crate::Storage + Default>() {
+            let mut bf = crate::GenericYabf::<S>::default();
+            let bits = [0_usize, 7, 8, 15, 16, 31, 32, 63, 64, 127, 128, 1000];
+            for b in bits.iter() {
+                bf.set_bit(*b, true);
+            }
+            for b in bits.iter() {
+                assert!(bf.bit(*b));
+                assert!(!bf.bit(*b + 1) || bits.contains(&(*b + 1)));
+            }
+            assert_eq!(bf.iter().collect::<Vec<usize>>(), bits.to_vec());
+            assert!(bf.capacity() > 1000);
+        }
+        check::<Vec<u8>>();
+        check::<Vec<u16>>();
+        check::<Vec<u32>>();
+        check::<Vec<u64>>();
+        check::<Vec<u128>>();
+    }
+
+    #[test]
+    fn test_debug() {
+        let mut bf = crate::GenericYabf::<Vec<u8>>::default();
+        assert_eq!(format!("{:?}", bf), "Yabf:0x0");
+        bf.set_bit(9, true);
+        assert_eq!(format!("{:?}", bf), "Yabf:0x02_00");
+        let mut bf = crate::GenericYabf::<Vec<u64>>::default();
+        bf.set_bit(0, true);
+        assert_eq!(format!("{:?}", bf), "Yabf:0x0000000000000001");
+    }
+
+    #[test]
+    fn test_shrink() {
+        let mut bf = crate::Yabf::default();
+        bf.set_bit(10_000_000, true);
+        assert!(bf.capacity() > 10_000_000);
+        bf.set_bit(10_000_000, false);
+        assert!(bf.capacity() > 10_000_000);
+        bf.set_bit(33, true);
+        bf.shrink_to_fit();
+        assert_eq!(bf.internal_len(), 2);
+        assert!(bf.capacity() < 10_000);
+        assert!(bf.bit(33));
+
+        bf.set_range(0..1000);
+        bf.truncate(64);
+        assert_eq!(bf.count_ones(), 64);
+        assert_eq!(bf.internal_len(), 2);
+        bf.truncate(10);
+        assert_eq!(
+            bf.iter().collect::<Vec<usize>>(),
+            (0..10).collect::<Vec<_>>()
+        );
+        assert_eq!(bf.internal_len(), 1);
+        bf.truncate(1000);
+        assert_eq!(bf.count_ones(), 10);
+        bf.truncate(0);
+        assert_eq!(bf.internal_len(), 0);
+        assert!(bf.is_empty());
+    }
+
     #[test]
     fn readme_1() {
         use crate::Yabf;
@@ -738,6 +530,11 @@ mod test {
 
 #[cfg(feature = "smallvec")]
 #[cfg(test)]
+#[allow(
+    clippy::bool_assert_comparison,
+    clippy::identity_op,
+    clippy::erasing_op
+)]
 mod test_small {
 
     #[test]
@@ -784,11 +581,55 @@ mod test_small {
     fn test_iter() {
         let mut bf = crate::SmallYabf::default();
         bf.set_bit(129, true);
-        println!("{:?}", bf.into_iter().collect::<Vec<usize>>());
-        assert_eq!(bf.into_iter().next().unwrap(), 129);
+        println!("{:?}", bf.iter().collect::<Vec<usize>>());
+        assert_eq!(bf.iter().next().unwrap(), 129);
         bf.set_bit(29, true);
         bf.set_bit(167, true);
-        println!("{:?}", bf.into_iter().collect::<Vec<usize>>());
+        println!("{:?}", bf.iter().collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn test_or() {
+        let mut a = crate::SmallYabf::default();
+        let mut b = crate::SmallYabf::default();
+        a.set_bit(45, true);
+        b.set_bit(4444, true);
+        a |= &b;
+        assert!(a.bit(45));
+        assert!(a.bit(4444));
+
+        // mixing storages
+        let mut c = crate::Yabf::default();
+        c.set_bit(3, true);
+        a |= &c;
+        assert_eq!(a.into_iter().collect::<Vec<usize>>(), vec![3, 45, 4444]);
+    }
+
+    #[test]
+    fn test_auto_trim() {
+        let mut bf = crate::AutoTrimSmallYabf::default();
+        assert_eq!(bf.capacity(), 4 * 32);
+        bf.set_bit(3, true);
+        bf.set_bit(1000, true);
+        assert!(bf.capacity() > 1000);
+        bf.set_bit(1000, false);
+        assert_eq!(bf.internal_len(), 1);
+        assert_eq!(bf.capacity(), 4 * 32);
+        assert!(bf.bit(3));
+
+        bf.set_range(0..1000);
+        bf.clear_range(100..);
+        assert_eq!(bf.internal_len(), 4);
+        assert_eq!(bf.capacity(), 4 * 32);
+        assert_eq!(bf.count_ones(), 100);
+
+        // a plain SmallYabf keeps its heap allocation
+        let mut bf = crate::SmallYabf::default();
+        bf.set_bit(1000, true);
+        bf.set_bit(1000, false);
+        assert!(bf.capacity() > 1000);
+        bf.shrink_to_fit();
+        assert_eq!(bf.capacity(), 4 * 32);
     }
 
     #[test]
@@ -0,0 +1,575 @@
+//! A native, versioned binary file format.
+
+use crate::{GenericYabf, Storage, Word};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// The magic bytes every file starts with
+const MAGIC: [u8; 4] = *b"YABF";
+/// The current format version
+const VERSION: u8 = 1;
+/// The size of the header in bytes
+const HEADER_LEN: usize = 24;
+/// The size of an encoded run in bytes
+const RUN_LEN: u64 = 16;
+
+/// The largest bit length [`GenericYabf::read_from`] and the human readable serde format
+/// accept, 2^32 bits or 512MiB of words.
+pub const MAX_BIT_LEN: u64 = 1 << 32;
+
+/// The ways the payload of a file can be encoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Encoding {
+    /// The words of the bit field, up to and including the last non-zero word
+    Dense = 0,
+    /// The maximal runs of set bits as pairs of `u64`, the start and the (exclusive) end
+    Runs = 1,
+}
+
+/// The errors reported when reading a bit field with [`GenericYabf::read_from`].
+#[derive(Debug)]
+pub enum ReadError {
+    /// The underlying reader failed, this includes unexpected end of file
+    Io(io::Error),
+    /// The file does not start with the magic bytes
+    BadMagic,
+    /// The file was written by an unknown version of the format
+    UnsupportedVersion(u8),
+    /// The word size in the header is not 8, 16, 32, 64 or 128
+    UnsupportedWordSize(u8),
+    /// The encoding in the header is unknown
+    UnknownEncoding(u8),
+    /// The bit length in the header exceeds the limit
+    TooLarge {
+        /// The bit length in the header
+        bit_len: u64,
+        /// The largest accepted bit length
+        limit: u64,
+    },
+    /// The checksum of the file does not match its content
+    ChecksumMismatch {
+        /// The checksum stored in the file
+        stored: u32,
+        /// The checksum of the read header and payload
+        computed: u32,
+    },
+    /// The header and the payload are inconsistent
+    Malformed(&'static str),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Io(e) => write!(f, "i/o error: {}", e),
+            ReadError::BadMagic => write!(f, "not a yabf file"),
+            ReadError::UnsupportedVersion(v) => write!(f, "unsupported format version {}", v),
+            ReadError::UnsupportedWordSize(w) => write!(f, "unsupported word size {}", w),
+            ReadError::UnknownEncoding(e) => write!(f, "unknown encoding {}", e),
+            ReadError::TooLarge { bit_len, limit } => {
+                write!(f, "bit length {} exceeds the limit {}", bit_len, limit)
+            }
+            ReadError::ChecksumMismatch { stored, computed } => write!(
+                f,
+                "checksum mismatch, stored {:08x} computed {:08x}",
+                stored, computed
+            ),
+            ReadError::Malformed(msg) => write!(f, "malformed file: {}", msg),
+        }
+    }
+}
+
+impl Error for ReadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReadError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ReadError {
+    #[inline]
+    fn from(e: io::Error) -> Self {
+        ReadError::Io(e)
+    }
+}
+
+impl<S: Storage> GenericYabf<S> {
+    /// Writes the bit field in the native file format. The smaller of the [`Encoding`]s is
+    /// used.
+    ///
+    /// Every file starts with a 24 byte header, all integers are little endian:
+    ///
+    /// | offset | size | content                                                |
+    /// |--------|------|--------------------------------------------------------|
+    /// | 0      | 4    | the magic bytes `YABF`                                 |
+    /// | 4      | 1    | the format version, currently 1                        |
+    /// | 5      | 1    | the number of bits in a payload word                   |
+    /// | 6      | 1    | the payload [`Encoding`]                               |
+    /// | 7      | 1    | reserved, always 0                                     |
+    /// | 8      | 8    | the bit length, i.e. the index of the last set bit + 1 |
+    /// | 16     | 8    | the length of the payload in bytes                     |
+    ///
+    /// The header is followed by the payload and a CRC-32 (IEEE) of the header and payload.
+    ///
+    /// The payload does not depend on the word type of the writer, a file written from a
+    /// `GenericYabf<Vec<u8>>` can be read into a `GenericYabf<Vec<u64>>` and vice versa.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let bf = Yabf::from_ranges(vec![3..5, 100..200]);
+    /// let mut file = Vec::new();
+    /// bf.write_to(&mut file).unwrap();
+    /// assert_eq!(Yabf::read_from(file.as_slice()).unwrap(), bf);
+    /// ```
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        let runs_len = self.runs().count() as u64 * RUN_LEN;
+        let encoding = if runs_len < self.dense_payload_len() {
+            Encoding::Runs
+        } else {
+            Encoding::Dense
+        };
+        self.write_to_with_encoding(writer, encoding)
+    }
+
+    /// Writes the bit field in the native file format, see [`write_to`](Self::write_to),
+    /// with the payload in the given `encoding`.
+    ///
+    /// ```
+    /// # use yabf::{Encoding, Yabf};
+    ///
+    /// let bf = Yabf::from_ranges(vec![3..5, 100..200]);
+    /// let mut file = Vec::new();
+    /// bf.write_to_with_encoding(&mut file, Encoding::Dense).unwrap();
+    /// assert_eq!(file[6], Encoding::Dense as u8);
+    /// assert_eq!(Yabf::read_from(file.as_slice()).unwrap(), bf);
+    /// ```
+    pub fn write_to_with_encoding<W: Write>(
+        &self,
+        writer: W,
+        encoding: Encoding,
+    ) -> io::Result<()> {
+        let bit_len = self.last_set().map_or(0, |b| b + 1);
+        let word_bytes = S::Word::BITS / 8;
+        let dense_len = self.dense_payload_len();
+
+        let mut writer = CrcWriter {
+            inner: writer,
+            crc: Crc32::new(),
+        };
+        let mut header = [0_u8; HEADER_LEN];
+        header[0..4].copy_from_slice(&MAGIC);
+        header[4] = VERSION;
+        header[5] = S::Word::BITS as u8;
+        header[6] = encoding as u8;
+        header[8..16].copy_from_slice(&(bit_len as u64).to_le_bytes());
+        let payload_len = match encoding {
+            Encoding::Dense => dense_len,
+            Encoding::Runs => self.runs().count() as u64 * RUN_LEN,
+        };
+        header[16..24].copy_from_slice(&payload_len.to_le_bytes());
+        writer.write_all(&header)?;
+
+        match encoding {
+            Encoding::Dense => {
+                let words = &self.internals.as_slice()[..dense_len as usize / word_bytes];
+                let mut buf = Vec::with_capacity(word_bytes);
+                for w in words.iter() {
+                    buf.clear();
+                    buf.extend((0..word_bytes).map(|i| (*w >> (i * 8)).to_u64() as u8));
+                    writer.write_all(&buf)?;
+                }
+            }
+            Encoding::Runs => {
+                for run in self.runs() {
+                    writer.write_all(&(run.start as u64).to_le_bytes())?;
+                    writer.write_all(&(run.end as u64).to_le_bytes())?;
+                }
+            }
+        }
+        let crc = writer.crc.finish();
+        writer.inner.write_all(&crc.to_le_bytes())
+    }
+
+    /// Returns the length in bytes of the dense payload, the words up to the last set bit
+    fn dense_payload_len(&self) -> u64 {
+        let bit_len = self.last_set().map_or(0, |b| b + 1);
+        ((bit_len + S::Word::BITS - 1) / S::Word::BITS * (S::Word::BITS / 8)) as u64
+    }
+
+    /// Reads a bit field in the native file format, see [`write_to`](Self::write_to). Files
+    /// with a bit length above [`MAX_BIT_LEN`] are rejected before anything is allocated.
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, ReadError> {
+        Self::read_from_with_limit(reader, MAX_BIT_LEN)
+    }
+
+    /// Reads a bit field in the native file format, rejecting files with a bit length above
+    /// `limit`.
+    ///
+    /// ```
+    /// # use yabf::{ReadError, Yabf};
+    ///
+    /// let mut file = Vec::new();
+    /// Yabf::from_ranges(vec![0..1000]).write_to(&mut file).unwrap();
+    /// assert!(matches!(
+    ///     Yabf::read_from_with_limit(file.as_slice(), 100),
+    ///     Err(ReadError::TooLarge { bit_len: 1000, limit: 100 })
+    /// ));
+    /// ```
+    pub fn read_from_with_limit<R: Read>(reader: R, limit: u64) -> Result<Self, ReadError> {
+        let mut reader = CrcReader {
+            inner: reader,
+            crc: Crc32::new(),
+        };
+        let mut header = [0_u8; HEADER_LEN];
+        reader.read_exact(&mut header)?;
+        if header[0..4] != MAGIC {
+            return Err(ReadError::BadMagic);
+        }
+        if header[4] != VERSION {
+            return Err(ReadError::UnsupportedVersion(header[4]));
+        }
+        let word_bits = header[5];
+        if !matches!(word_bits, 8 | 16 | 32 | 64 | 128) {
+            return Err(ReadError::UnsupportedWordSize(word_bits));
+        }
+        let encoding = match header[6] {
+            0 => Encoding::Dense,
+            1 => Encoding::Runs,
+            e => return Err(ReadError::UnknownEncoding(e)),
+        };
+        if header[7] != 0 {
+            return Err(ReadError::Malformed("reserved byte is not zero"));
+        }
+        let bit_len = read_u64(&header[8..16]);
+        let payload_len = read_u64(&header[16..24]);
+        let limit = limit.min(usize::MAX as u64 / 2);
+        if bit_len > limit {
+            return Err(ReadError::TooLarge { bit_len, limit });
+        }
+        let max_payload_len = match encoding {
+            Encoding::Dense => {
+                let word_bits = u64::from(word_bits);
+                (bit_len + word_bits - 1) / word_bits * word_bits / 8
+            }
+            // runs are separated by at least one unset bit
+            Encoding::Runs => (bit_len + 1) / 2 * RUN_LEN,
+        };
+        if payload_len > max_payload_len
+            || (encoding == Encoding::Dense && payload_len != max_payload_len)
+            || (encoding == Encoding::Runs && payload_len % RUN_LEN != 0)
+        {
+            return Err(ReadError::Malformed(
+                "payload length does not match bit length",
+            ));
+        }
+
+        let mut payload = Vec::new();
+        let read = (&mut reader).take(payload_len).read_to_end(&mut payload)?;
+        if read as u64 != payload_len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let computed = reader.crc.finish();
+        let mut stored = [0_u8; 4];
+        reader.inner.read_exact(&mut stored)?;
+        let stored = u32::from_le_bytes(stored);
+        if stored != computed {
+            return Err(ReadError::ChecksumMismatch { stored, computed });
+        }
+
+        let rv = match encoding {
+            Encoding::Dense => Self::from_bytes_le(&payload),
+            Encoding::Runs => {
+                let runs: Vec<(u64, u64)> = payload
+                    .chunks(RUN_LEN as usize)
+                    .map(|run| (read_u64(&run[0..8]), read_u64(&run[8..16])))
+                    .collect();
+                let mut prev_end = None;
+                for &(start, end) in runs.iter() {
+                    if start >= end || end > bit_len || prev_end.map_or(false, |p| start <= p) {
+                        return Err(ReadError::Malformed("runs are not ordered and disjoint"));
+                    }
+                    prev_end = Some(end);
+                }
+                // only allocate what the validated runs cover, not what the header claims
+                let mut rv = Self::with_capacity(prev_end.unwrap_or(0) as usize);
+                for &(start, end) in runs.iter() {
+                    rv.set_range(start as usize..end as usize);
+                }
+                rv
+            }
+        };
+        if rv.last_set().map_or(0, |b| b as u64 + 1) != bit_len {
+            return Err(ReadError::Malformed(
+                "last set bit does not match bit length",
+            ));
+        }
+        Ok(rv)
+    }
+}
+
+/// Decodes a little endian `u64`, `bytes` must be 8 bytes long
+#[inline]
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut buf = [0_u8; 8];
+    buf.copy_from_slice(bytes);
+    u64::from_le_bytes(buf)
+}
+
+/// The CRC-32 (IEEE 802.3) lookup table
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0_u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xEDB8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+/// An incremental CRC-32 (IEEE 802.3) checksum
+struct Crc32(u32);
+
+impl Crc32 {
+    #[inline]
+    fn new() -> Self {
+        Crc32(0xFFFF_FFFF)
+    }
+
+    #[inline]
+    fn update(&mut self, bytes: &[u8]) {
+        for b in bytes.iter() {
+            self.0 = CRC_TABLE[((self.0 ^ u32::from(*b)) & 0xFF) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    #[inline]
+    fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+/// A writer that updates a checksum with everything written
+struct CrcWriter<W> {
+    inner: W,
+    crc: Crc32,
+}
+
+impl<W: Write> Write for CrcWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.crc.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A reader that updates a checksum with everything read
+struct CrcReader<R> {
+    inner: R,
+    crc: Crc32,
+}
+
+impl<R: Read> Read for CrcReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.crc.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Crc32, ReadError, HEADER_LEN};
+    use crate::GenericYabf;
+
+    fn round_trip<S: crate::Storage, T: crate::Storage<Word = S::Word>>(
+        bf: &GenericYabf<S>,
+    ) -> (Vec<u8>, GenericYabf<T>) {
+        let mut file = Vec::new();
+        bf.write_to(&mut file).unwrap();
+        let back = GenericYabf::<T>::read_from(file.as_slice()).unwrap();
+        (file, back)
+    }
+
+    fn check<S: crate::Storage + Default>() {
+        let sparse = GenericYabf::<S>::from_ranges(vec![3..5, 1000..1200]);
+        let (file, back) = round_trip::<S, S>(&sparse);
+        assert_eq!(back, sparse);
+        assert_eq!(file[6], super::Encoding::Runs as u8);
+        assert_eq!(file.len(), HEADER_LEN + 2 * 16 + 4);
+
+        let dense: GenericYabf<S> = (0..1000).step_by(3).collect();
+        let (file, back) = round_trip::<S, S>(&dense);
+        assert_eq!(back, dense);
+        assert_eq!(file[6], super::Encoding::Dense as u8);
+
+        let mut empty = GenericYabf::<S>::default();
+        empty.set_bit(500, true);
+        empty.set_bit(500, false);
+        let (file, back) = round_trip::<S, S>(&empty);
+        assert!(back.is_empty());
+        assert_eq!(file.len(), HEADER_LEN + 4);
+
+        // forced encodings
+        for bf in [&sparse, &dense].iter() {
+            for encoding in [super::Encoding::Dense, super::Encoding::Runs].iter() {
+                let mut file = Vec::new();
+                bf.write_to_with_encoding(&mut file, *encoding).unwrap();
+                assert_eq!(file[6], *encoding as u8);
+                assert_eq!(&GenericYabf::<S>::read_from(file.as_slice()).unwrap(), *bf);
+            }
+        }
+
+        // a different word size
+        let bytes: GenericYabf<Vec<u8>> = dense.iter().collect();
+        let mut file = Vec::new();
+        bytes.write_to(&mut file).unwrap();
+        let back = GenericYabf::<S>::read_from(file.as_slice()).unwrap();
+        assert_eq!(back, dense);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        check::<Vec<u8>>();
+        check::<Vec<u32>>();
+        check::<Vec<u64>>();
+        check::<Vec<u128>>();
+        #[cfg(feature = "smallvec")]
+        check::<smallvec::SmallVec<[u32; 4]>>();
+    }
+
+    #[test]
+    fn test_crc() {
+        let mut crc = Crc32::new();
+        crc.update(b"123456789");
+        assert_eq!(crc.finish(), 0xCBF4_3926);
+    }
+
+    /// Rewrites the header field at `offset` and fixes the checksum
+    fn patch(file: &[u8], offset: usize, bytes: &[u8]) -> Vec<u8> {
+        let mut file = file.to_vec();
+        file[offset..offset + bytes.len()].copy_from_slice(bytes);
+        let len = file.len() - 4;
+        let mut crc = Crc32::new();
+        crc.update(&file[..len]);
+        file[len..].copy_from_slice(&crc.finish().to_le_bytes());
+        file
+    }
+
+    fn read(file: &[u8]) -> Result<crate::Yabf, ReadError> {
+        crate::Yabf::read_from(file)
+    }
+
+    #[test]
+    fn test_errors() {
+        let bf: crate::Yabf = (0..300).step_by(2).collect();
+        let mut file = Vec::new();
+        bf.write_to(&mut file).unwrap();
+        assert!(read(&file).is_ok());
+
+        for i in 0..file.len() {
+            let mut corrupt = file.clone();
+            corrupt[i] ^= 0x10;
+            assert!(read(&corrupt).is_err(), "corrupt byte {}", i);
+        }
+        let mut corrupt = file.clone();
+        corrupt[HEADER_LEN] ^= 1;
+        assert!(matches!(
+            read(&corrupt),
+            Err(ReadError::ChecksumMismatch { .. })
+        ));
+        match read(&file[..file.len() - 1]) {
+            Err(ReadError::Io(e)) => assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof),
+            _ => panic!("expected an i/o error"),
+        }
+        assert!(matches!(read(&file[..30]), Err(ReadError::Io(_))));
+        assert!(matches!(
+            read(&patch(&file, 0, b"XABF")),
+            Err(ReadError::BadMagic)
+        ));
+        assert!(matches!(
+            read(&patch(&file, 4, &[2])),
+            Err(ReadError::UnsupportedVersion(2))
+        ));
+        assert!(matches!(
+            read(&patch(&file, 5, &[12])),
+            Err(ReadError::UnsupportedWordSize(12))
+        ));
+        assert!(matches!(
+            read(&patch(&file, 6, &[7])),
+            Err(ReadError::UnknownEncoding(7))
+        ));
+        assert!(matches!(
+            read(&patch(&file, 7, &[1])),
+            Err(ReadError::Malformed(_))
+        ));
+        // hostile lengths are rejected before the payload is read
+        assert!(matches!(
+            read(&patch(&file, 8, &u64::MAX.to_le_bytes())),
+            Err(ReadError::TooLarge { .. })
+        ));
+        assert!(matches!(
+            read(&patch(&file, 16, &u64::MAX.to_le_bytes())),
+            Err(ReadError::Malformed(_))
+        ));
+        // a bit length beyond the last set bit
+        assert!(matches!(
+            read(&patch(&file, 8, &400_u64.to_le_bytes())),
+            Err(ReadError::Malformed(_))
+        ));
+        let shown = ReadError::TooLarge {
+            bit_len: 10,
+            limit: 5,
+        }
+        .to_string();
+        assert_eq!(shown, "bit length 10 exceeds the limit 5");
+    }
+
+    #[test]
+    fn test_bad_runs() {
+        let bf = crate::Yabf::from_ranges(vec![10..20, 1000..1010]);
+        let mut file = Vec::new();
+        bf.write_to(&mut file).unwrap();
+        assert_eq!(file[6], super::Encoding::Runs as u8);
+        // overlapping runs
+        let corrupt = patch(&file, HEADER_LEN + 16, &15_u64.to_le_bytes());
+        assert!(matches!(read(&corrupt), Err(ReadError::Malformed(_))));
+        // an empty run
+        let corrupt = patch(&file, HEADER_LEN + 8, &10_u64.to_le_bytes());
+        assert!(matches!(read(&corrupt), Err(ReadError::Malformed(_))));
+    }
+
+    #[test]
+    fn test_huge_empty_runs() {
+        // a runs header with a huge bit length and no runs must fail without allocating
+        let mut file = Vec::new();
+        crate::Yabf::default().write_to(&mut file).unwrap();
+        let file = patch(&file, 6, &[super::Encoding::Runs as u8]);
+        let file = patch(&file, 8, &(1_u64 << 50).to_le_bytes());
+        assert!(matches!(
+            crate::Yabf::read_from_with_limit(file.as_slice(), u64::MAX),
+            Err(ReadError::Malformed(_))
+        ));
+        let file = patch(&file, 8, &super::MAX_BIT_LEN.to_le_bytes());
+        assert!(matches!(read(&file), Err(ReadError::Malformed(_))));
+    }
+}
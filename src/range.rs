@@ -0,0 +1,164 @@
+//! Mutation of whole ranges of bits.
+
+use crate::word::low_mask;
+use crate::{to_range, GenericYabf, Storage, Word};
+use std::ops::{Range, RangeBounds};
+
+impl<S: Storage> GenericYabf<S> {
+    /// Sets all the bits within `range` to `true`. Whole words are filled at once and the
+    /// storage grows at most once.
+    /// An unbounded range end means the end of the used region, i.e. `internal_len()` words.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let mut bf = Yabf::default();
+    /// bf.set_range(10..10_010);
+    /// assert_eq!(bf.count_ones(), 10_000);
+    /// assert!(bf.bit(10) && bf.bit(10_009) && !bf.bit(10_010));
+    /// ```
+    #[inline]
+    pub fn set_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        let range = self.resolve_range(&range);
+        self.grow_to(&range);
+        self.apply_range(range, |w, mask| *w |= mask);
+    }
+
+    /// Sets all the bits within `range` to `false`. The storage never grows.
+    /// An unbounded range end means the end of the used region, i.e. `internal_len()` words.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let mut bf = Yabf::default();
+    /// bf.set_range(0..100);
+    /// bf.clear_range(10..);
+    /// assert_eq!(bf.count_ones(), 10);
+    /// ```
+    #[inline]
+    pub fn clear_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        let mut range = self.resolve_range(&range);
+        range.end = range.end.min(self.internal_len() * S::Word::BITS);
+        self.apply_range(range, |w, mask| *w &= !mask);
+        self.internals.bits_cleared();
+    }
+
+    /// Inverts all the bits within `range`. The storage grows at most once.
+    /// An unbounded range end means the end of the used region, i.e. `internal_len()` words.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let mut bf = Yabf::default();
+    /// bf.set_range(0..10);
+    /// bf.toggle_range(5..15);
+    /// assert_eq!(bf.into_iter().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 10, 11, 12, 13, 14]);
+    /// ```
+    #[inline]
+    pub fn toggle_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        let range = self.resolve_range(&range);
+        self.grow_to(&range);
+        self.apply_range(range, |w, mask| *w ^= mask);
+        self.internals.bits_cleared();
+    }
+
+    /// Converts `range` into a `Range`, an unbounded end becomes the end of the used region.
+    #[inline]
+    fn resolve_range<R: RangeBounds<usize>>(&self, range: &R) -> Range<usize> {
+        let mut rv = to_range(range);
+        if let std::ops::Bound::Unbounded = range.end_bound() {
+            rv.end = self.internal_len() * S::Word::BITS;
+        }
+        rv
+    }
+
+    /// Makes sure that the storage is large enough to hold every bit of `range`.
+    #[inline]
+    fn grow_to(&mut self, range: &Range<usize>) {
+        if range.start < range.end {
+            let last_word = (range.end - 1) / S::Word::BITS;
+            if last_word >= self.internal_len() {
+                self.internals.resize(last_word + 1);
+            }
+        }
+    }
+
+    /// Applies `op(word, mask)` on every word overlapping `range`, where `mask` contains the
+    /// bits of the word that are within the range. The storage must already be large enough.
+    fn apply_range<F: Fn(&mut S::Word, S::Word)>(&mut self, range: Range<usize>, op: F) {
+        if range.start >= range.end {
+            return;
+        }
+        let words = self.internals.as_mut_slice();
+        let first_word = range.start / S::Word::BITS;
+        let last_word = (range.end - 1) / S::Word::BITS;
+        let first_mask = !low_mask::<S::Word>(range.start % S::Word::BITS);
+        let last_mask = low_mask::<S::Word>((range.end - 1) % S::Word::BITS + 1);
+        if first_word == last_word {
+            op(&mut words[first_word], first_mask & last_mask);
+        } else {
+            op(&mut words[first_word], first_mask);
+            for w in words[first_word + 1..last_word].iter_mut() {
+                op(w, S::Word::MAX);
+            }
+            op(&mut words[last_word], last_mask);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::GenericYabf;
+
+    fn check<S: crate::Storage + Default>() {
+        for start in 0..70 {
+            for end in (start..300).step_by(7) {
+                let mut bf = GenericYabf::<S>::default();
+                bf.set_bit(1, true);
+                bf.set_range(start..end);
+                let expected: Vec<usize> = (0..end.max(2))
+                    .filter(|b| *b == 1 || (start..end).contains(b))
+                    .collect();
+                assert_eq!(bf.iter().collect::<Vec<_>>(), expected);
+
+                bf.toggle_range(start / 2..=end);
+                let toggled: Vec<usize> = (0..=end + 1)
+                    .filter(|b| expected.contains(b) != (start / 2..=end).contains(b))
+                    .collect();
+                assert_eq!(bf.iter().collect::<Vec<_>>(), toggled);
+
+                bf.clear_range(start..end);
+                let cleared: Vec<usize> = toggled
+                    .into_iter()
+                    .filter(|b| !(start..end).contains(b))
+                    .collect();
+                assert_eq!(bf.iter().collect::<Vec<_>>(), cleared);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ranges() {
+        check::<Vec<u8>>();
+        check::<Vec<u32>>();
+        check::<Vec<u128>>();
+        #[cfg(feature = "smallvec")]
+        check::<smallvec::SmallVec<[u32; 4]>>();
+    }
+
+    #[test]
+    fn test_unbounded() {
+        let mut bf = crate::Yabf::default();
+        bf.set_bit(40, true);
+        bf.set_range(60..);
+        assert_eq!(bf.count_ones(), 5);
+        bf.toggle_range(..);
+        assert_eq!(bf.count_ones(), 59);
+        bf.clear_range(..usize::MAX);
+        assert!(bf.is_empty());
+        assert_eq!(bf.internal_len(), 2);
+        // clearing never grows the storage
+        bf.clear_range(0..10_000);
+        assert_eq!(bf.internal_len(), 2);
+    }
+}
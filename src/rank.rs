@@ -0,0 +1,352 @@
+//! Population count, rank and select queries.
+//!
+//! The queries on [`GenericYabf`] scan the words from the start of the bit field. For large,
+//! read-mostly bit fields a [`RankSelect`] index can be built that answers the same queries
+//! by looking at a few words only.
+
+use crate::word::{low_mask, select_in_word};
+use crate::{to_range, GenericYabf, Storage, Word};
+use std::ops::RangeBounds;
+
+impl<S: Storage> GenericYabf<S> {
+    /// Returns the number of bits set to `true`.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let mut bf = Yabf::default();
+    /// bf.set_bit(3, true);
+    /// bf.set_bit(300, true);
+    /// assert_eq!(bf.count_ones(), 2);
+    /// ```
+    #[inline]
+    pub fn count_ones(&self) -> usize {
+        self.internals
+            .as_slice()
+            .iter()
+            .map(|w| w.count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns the number of bits set to `false` within the used region, i.e. the first
+    /// `internal_len()` words.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let mut bf = Yabf::default();
+    /// bf.set_bit(3, true);
+    /// assert_eq!(bf.count_zeros(), 31);
+    /// ```
+    #[inline]
+    pub fn count_zeros(&self) -> usize {
+        self.internal_len() * S::Word::BITS - self.count_ones()
+    }
+
+    /// Returns the number of set bits strictly below bit `n`.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let mut bf = Yabf::default();
+    /// bf.set_bit(3, true);
+    /// bf.set_bit(300, true);
+    /// assert_eq!(bf.rank(3), 0);
+    /// assert_eq!(bf.rank(4), 1);
+    /// assert_eq!(bf.rank(usize::MAX), 2);
+    /// ```
+    pub fn rank(&self, n: usize) -> usize {
+        let words = self.internals.as_slice();
+        let word = n / S::Word::BITS;
+        if word >= words.len() {
+            return self.count_ones();
+        }
+        let full: usize = words[..word].iter().map(|w| w.count_ones() as usize).sum();
+        let partial = words[word] & low_mask::<S::Word>(n % S::Word::BITS);
+        full + partial.count_ones() as usize
+    }
+
+    /// Returns the number of set bits within `range`.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let mut bf = Yabf::default();
+    /// bf.set_bit(3, true);
+    /// bf.set_bit(40, true);
+    /// bf.set_bit(300, true);
+    /// assert_eq!(bf.count_in_range(3..300), 2);
+    /// assert_eq!(bf.count_in_range(4..=300), 2);
+    /// assert_eq!(bf.count_in_range(..), 3);
+    /// ```
+    pub fn count_in_range<R: RangeBounds<usize>>(&self, range: R) -> usize {
+        let words = self.internals.as_slice();
+        let range = to_range(&range);
+        let end = range.end.min(words.len() * S::Word::BITS);
+        if range.start >= end {
+            return 0;
+        }
+        let first_word = range.start / S::Word::BITS;
+        let last_word = (end - 1) / S::Word::BITS;
+        let first_mask = !low_mask::<S::Word>(range.start % S::Word::BITS);
+        let last_mask = low_mask::<S::Word>((end - 1) % S::Word::BITS + 1);
+        if first_word == last_word {
+            return (words[first_word] & first_mask & last_mask).count_ones() as usize;
+        }
+        let middle: usize = words[first_word + 1..last_word]
+            .iter()
+            .map(|w| w.count_ones() as usize)
+            .sum();
+        middle
+            + (words[first_word] & first_mask).count_ones() as usize
+            + (words[last_word] & last_mask).count_ones() as usize
+    }
+
+    /// Returns the position of the `k`:th (zero based) set bit, or `None` if fewer than
+    /// `k + 1` bits are set.
+    /// This is a relatively expensive O(size of container) operation, see [`RankSelect`]
+    /// for a faster alternative.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let mut bf = Yabf::default();
+    /// bf.set_bit(3, true);
+    /// bf.set_bit(300, true);
+    /// assert_eq!(bf.select(0), Some(3));
+    /// assert_eq!(bf.select(1), Some(300));
+    /// assert_eq!(bf.select(2), None);
+    /// ```
+    pub fn select(&self, k: usize) -> Option<usize> {
+        let mut remaining = k;
+        for (i, w) in self.internals.as_slice().iter().enumerate() {
+            let ones = w.count_ones() as usize;
+            if remaining < ones {
+                return Some(i * S::Word::BITS + select_in_word(*w, remaining));
+            }
+            remaining -= ones;
+        }
+        None
+    }
+}
+
+/// The number of bits covered by each superblock of a [`RankSelect`] index
+const SUPERBLOCK_BITS: usize = 512;
+/// A select sample is stored for every `SELECT_SAMPLE`:th set bit
+const SELECT_SAMPLE: usize = 4096;
+
+/// A rank/select index over a bit field.
+///
+/// The index stores the number of set bits preceding every 512 bit superblock, and the
+/// superblock of every 4096:th set bit. Rank queries then only need to count the bits of
+/// a single superblock, select queries do a short binary search between two samples.
+///
+/// The index borrows the bit field, so it can not go stale.
+///
+/// ```
+/// # use yabf::{RankSelect, Yabf};
+///
+/// let mut bf = Yabf::default();
+/// for i in (0..100_000).step_by(3) {
+///     bf.set_bit(i, true);
+/// }
+/// let index = RankSelect::new(&bf);
+/// assert_eq!(index.count_ones(), bf.count_ones());
+/// assert_eq!(index.rank(30_000), 10_000);
+/// assert_eq!(index.select(10_000), Some(30_000));
+/// ```
+#[derive(Clone, Debug)]
+pub struct RankSelect<'a, S: Storage> {
+    yabf: &'a GenericYabf<S>,
+    // ranks[i] is the number of set bits before superblock i, the last element is the total
+    ranks: Vec<usize>,
+    // samples[i] is the superblock containing set bit number i * SELECT_SAMPLE
+    samples: Vec<usize>,
+}
+
+impl<'a, S: Storage> RankSelect<'a, S> {
+    /// The number of words in each superblock
+    const BLOCK_WORDS: usize = if SUPERBLOCK_BITS > S::Word::BITS {
+        SUPERBLOCK_BITS / S::Word::BITS
+    } else {
+        1
+    };
+
+    /// Build a rank/select index over `yabf`. This is an O(size of container) operation.
+    pub fn new(yabf: &'a GenericYabf<S>) -> Self {
+        let words = yabf.internals.as_slice();
+        let mut ranks = Vec::with_capacity(words.len() / Self::BLOCK_WORDS + 2);
+        let mut samples = Vec::new();
+        let mut total = 0_usize;
+        for (block, chunk) in words.chunks(Self::BLOCK_WORDS).enumerate() {
+            ranks.push(total);
+            let ones: usize = chunk.iter().map(|w| w.count_ones() as usize).sum();
+            // record every sample point that falls within this block
+            while samples.len() * SELECT_SAMPLE < total + ones {
+                samples.push(block);
+            }
+            total += ones;
+        }
+        ranks.push(total);
+        Self {
+            yabf,
+            ranks,
+            samples,
+        }
+    }
+
+    /// Returns the indexed bit field
+    #[inline]
+    pub fn yabf(&self) -> &'a GenericYabf<S> {
+        self.yabf
+    }
+
+    /// Returns the number of bits set to `true`. O(1)
+    #[inline]
+    pub fn count_ones(&self) -> usize {
+        *self.ranks.last().unwrap()
+    }
+
+    /// Returns the number of set bits strictly below bit `n`.
+    pub fn rank(&self, n: usize) -> usize {
+        let words = self.yabf.internals.as_slice();
+        let word = n / S::Word::BITS;
+        if word >= words.len() {
+            return self.count_ones();
+        }
+        let block = word / Self::BLOCK_WORDS;
+        let full: usize = words[block * Self::BLOCK_WORDS..word]
+            .iter()
+            .map(|w| w.count_ones() as usize)
+            .sum();
+        let partial = words[word] & low_mask::<S::Word>(n % S::Word::BITS);
+        self.ranks[block] + full + partial.count_ones() as usize
+    }
+
+    /// Returns the position of the `k`:th (zero based) set bit, or `None` if fewer than
+    /// `k + 1` bits are set.
+    pub fn select(&self, k: usize) -> Option<usize> {
+        if k >= self.count_ones() {
+            return None;
+        }
+        // the sought block lies between the two surrounding samples
+        let sample = k / SELECT_SAMPLE;
+        let low = self.samples[sample];
+        let high = self
+            .samples
+            .get(sample + 1)
+            .map_or(self.ranks.len() - 1, |b| b + 1);
+        // the last block in low..high with ranks[block] <= k
+        let block = low + self.ranks[low..high].partition_point(|r| *r <= k) - 1;
+
+        let words = self.yabf.internals.as_slice();
+        let mut remaining = k - self.ranks[block];
+        let start = block * Self::BLOCK_WORDS;
+        for (i, w) in words[start..].iter().enumerate() {
+            let ones = w.count_ones() as usize;
+            if remaining < ones {
+                return Some((start + i) * S::Word::BITS + select_in_word(*w, remaining));
+            }
+            remaining -= ones;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::GenericYabf;
+
+    fn check<S: crate::Storage + Default>() {
+        let bits = [0_usize, 1, 7, 8, 31, 32, 33, 64, 100, 127, 128, 129, 500];
+        let mut bf = GenericYabf::<S>::default();
+        for b in bits.iter() {
+            bf.set_bit(*b, true);
+        }
+        assert_eq!(bf.count_ones(), bits.len());
+        assert_eq!(
+            bf.count_zeros() + bf.count_ones(),
+            bf.internal_len() * <S::Word as crate::Word>::BITS
+        );
+        for n in 0..600 {
+            let expected = bits.iter().filter(|b| **b < n).count();
+            assert_eq!(bf.rank(n), expected, "rank({})", n);
+        }
+        for start in 0..140 {
+            for end in start..600 {
+                let expected = bits.iter().filter(|b| **b >= start && **b < end).count();
+                assert_eq!(
+                    bf.count_in_range(start..end),
+                    expected,
+                    "{}..{}",
+                    start,
+                    end
+                );
+            }
+        }
+        assert_eq!(bf.count_in_range(..), bits.len());
+        assert_eq!(bf.count_in_range(129..=129), 1);
+        assert_eq!(bf.count_in_range(10000..), 0);
+    }
+
+    #[test]
+    fn test_count_and_rank() {
+        check::<Vec<u8>>();
+        check::<Vec<u32>>();
+        check::<Vec<u64>>();
+        check::<Vec<u128>>();
+        #[cfg(feature = "smallvec")]
+        check::<smallvec::SmallVec<[u32; 4]>>();
+    }
+
+    fn check_index<S: crate::Storage + Default>() {
+        let mut bf = GenericYabf::<S>::default();
+        let mut bits = Vec::new();
+        // a dense region, a long gap and a sparse region
+        for i in (0..20_000).filter(|i| i % 3 != 0) {
+            bf.set_bit(i, true);
+            bits.push(i);
+        }
+        for i in (100_000..200_000).step_by(997) {
+            bf.set_bit(i, true);
+            bits.push(i);
+        }
+        let index = crate::RankSelect::new(&bf);
+        assert_eq!(index.count_ones(), bits.len());
+        for (k, b) in bits.iter().enumerate() {
+            assert_eq!(index.select(k), Some(*b));
+            assert_eq!(bf.select(k), Some(*b));
+            assert_eq!(index.rank(*b), k);
+            assert_eq!(index.rank(*b + 1), k + 1);
+        }
+        assert_eq!(index.select(bits.len()), None);
+        for n in (0..210_000).step_by(101) {
+            assert_eq!(index.rank(n), bf.rank(n));
+        }
+    }
+
+    #[test]
+    fn test_rank_select_index() {
+        check_index::<Vec<u8>>();
+        check_index::<Vec<u32>>();
+        check_index::<Vec<u64>>();
+        check_index::<Vec<u128>>();
+        #[cfg(feature = "smallvec")]
+        check_index::<smallvec::SmallVec<[u64; 4]>>();
+
+        let bf = crate::Yabf::default();
+        let index = crate::RankSelect::new(&bf);
+        assert_eq!(index.count_ones(), 0);
+        assert_eq!(index.rank(10), 0);
+        assert_eq!(index.select(0), None);
+    }
+
+    #[test]
+    fn test_empty() {
+        let bf = crate::Yabf::default();
+        assert_eq!(bf.count_ones(), 0);
+        assert_eq!(bf.count_zeros(), 0);
+        assert_eq!(bf.rank(100), 0);
+        assert_eq!(bf.count_in_range(0..100), 0);
+    }
+}
@@ -0,0 +1,713 @@
+//! A compressed bit field for sparse and clustered indices, in the style of Roaring bitmaps.
+//!
+//! The index space is split into chunks of 2^16 bits. Only the chunks holding set bits are
+//! stored, each in the smallest of three containers:
+//!  * a sorted array of the low 16 bits of the indices, for up to 4096 set bits
+//!  * a plain bitmap of 1024 `u64` words, for more than 4096 set bits
+//!  * a sorted list of runs, chosen by [`RoaringYabf::run_optimize`] when it is smaller
+
+use crate::bitops::impl_binary_op;
+use crate::{GenericYabf, Storage, YabfIterator};
+use std::iter::{FromIterator, FusedIterator};
+use std::ops;
+
+/// The number of bits in a chunk
+const CHUNK_BITS: usize = 1 << 16;
+/// The number of words in a bitmap container
+const CHUNK_WORDS: usize = CHUNK_BITS / 64;
+/// The largest number of set bits stored in an array container
+const ARRAY_MAX: usize = 4096;
+
+/// The bits of one chunk
+#[derive(Clone, Debug)]
+enum Container {
+    /// The sorted low bits of the set indices
+    Array(Vec<u16>),
+    /// A bitmap and the number of bits set in it
+    Bitmap(GenericYabf<Vec<u64>>, usize),
+    /// Sorted, disjoint and non adjacent runs of set bits, the first and the last bit (inclusive)
+    Runs(Vec<(u16, u16)>),
+}
+
+impl Container {
+    /// Builds the smaller of an array and a bitmap container from the words of a chunk, or
+    /// `None` if no bit is set.
+    fn from_words(mut words: Vec<u64>) -> Option<Container> {
+        let count: usize = words.iter().map(|w| w.count_ones() as usize).sum();
+        if count == 0 {
+            return None;
+        }
+        words.resize(CHUNK_WORDS, 0);
+        let bitmap = GenericYabf::from_storage(words);
+        Some(if count <= ARRAY_MAX {
+            Container::Array(bitmap.iter().map(|i| i as u16).collect())
+        } else {
+            Container::Bitmap(bitmap, count)
+        })
+    }
+
+    /// Builds the smaller of an array and a bitmap container from the sorted low bits of a
+    /// chunk, or `None` if no bit is set.
+    fn from_array(array: Vec<u16>) -> Option<Container> {
+        if array.is_empty() {
+            None
+        } else if array.len() <= ARRAY_MAX {
+            Some(Container::Array(array))
+        } else {
+            let count = array.len();
+            let mut bitmap: GenericYabf<Vec<u64>> = array.into_iter().map(usize::from).collect();
+            bitmap.internals.resize(CHUNK_WORDS, 0);
+            Some(Container::Bitmap(bitmap, count))
+        }
+    }
+
+    /// Combines two containers of the same chunk word by word with `op`, which must map
+    /// `(0, 0)` to `0`. Arrays are combined without expanding them to bitmaps.
+    fn combine(a: &Container, b: &Container, op: fn(u64, u64) -> u64) -> Option<Container> {
+        // the result for a single bit set in `a`, `b` or both
+        let bit = |x: bool, y: bool| op(x as u64, y as u64) & 1 != 0;
+        match (a, b) {
+            (Container::Array(a), Container::Array(b)) => {
+                let mut array = Vec::new();
+                let (mut i, mut j) = (0, 0);
+                loop {
+                    let (low, in_a, in_b) = match (a.get(i), b.get(j)) {
+                        (Some(x), Some(y)) if x == y => (*x, true, true),
+                        (Some(x), Some(y)) if x > y => (*y, false, true),
+                        (Some(x), _) => (*x, true, false),
+                        (None, Some(y)) => (*y, false, true),
+                        (None, None) => break,
+                    };
+                    i += in_a as usize;
+                    j += in_b as usize;
+                    if bit(in_a, in_b) {
+                        array.push(low);
+                    }
+                }
+                Container::from_array(array)
+            }
+            (Container::Array(array), Container::Bitmap(bitmap, _)) => {
+                if op(0, u64::MAX) == 0 {
+                    // the result is a subset of the array
+                    let low = |x: &&u16| bit(true, bitmap.bit(**x as usize));
+                    Container::from_array(array.iter().filter(low).copied().collect())
+                } else {
+                    Container::from_words(patch_words(array, bitmap, op))
+                }
+            }
+            (Container::Bitmap(bitmap, _), Container::Array(array)) => {
+                if op(u64::MAX, 0) == 0 {
+                    // the result is a subset of the array
+                    let low = |x: &&u16| bit(bitmap.bit(**x as usize), true);
+                    Container::from_array(array.iter().filter(low).copied().collect())
+                } else {
+                    Container::from_words(patch_words(array, bitmap, |x, y| op(y, x)))
+                }
+            }
+            _ => {
+                let (wa, wb) = (a.to_words(), b.to_words());
+                Container::from_words(wa.iter().zip(wb.iter()).map(|(x, y)| op(*x, *y)).collect())
+            }
+        }
+    }
+
+    /// Returns the chunk as a bitmap of `CHUNK_WORDS` words
+    fn to_words(&self) -> Vec<u64> {
+        let mut bitmap = match self {
+            Container::Bitmap(bitmap, _) => bitmap.clone(),
+            Container::Array(array) => array.iter().map(|i| *i as usize).collect(),
+            Container::Runs(runs) => {
+                GenericYabf::from_ranges(runs.iter().map(|(s, l)| *s as usize..*l as usize + 1))
+            }
+        }
+        .into_storage();
+        bitmap.resize(CHUNK_WORDS, 0);
+        bitmap
+    }
+
+    /// Returns the number of set bits
+    fn len(&self) -> usize {
+        match self {
+            Container::Array(array) => array.len(),
+            Container::Bitmap(_, count) => *count,
+            Container::Runs(runs) => runs.iter().map(|(s, l)| (l - s) as usize + 1).sum(),
+        }
+    }
+
+    fn contains(&self, low: u16) -> bool {
+        match self {
+            Container::Array(array) => array.binary_search(&low).is_ok(),
+            Container::Bitmap(bitmap, _) => bitmap.bit(low as usize),
+            Container::Runs(runs) => {
+                let i = runs.partition_point(|(s, _)| *s <= low);
+                i > 0 && runs[i - 1].1 >= low
+            }
+        }
+    }
+
+    /// Sets bit `low`, returns true if it was not set before
+    fn insert(&mut self, low: u16) -> bool {
+        match self {
+            Container::Array(array) => match array.binary_search(&low) {
+                Ok(_) => false,
+                Err(pos) => {
+                    if array.len() < ARRAY_MAX {
+                        array.insert(pos, low);
+                    } else {
+                        let mut bitmap: GenericYabf<Vec<u64>> =
+                            array.iter().map(|i| *i as usize).collect();
+                        bitmap.set_bit(low as usize, true);
+                        *self = Container::Bitmap(bitmap, ARRAY_MAX + 1);
+                    }
+                    true
+                }
+            },
+            Container::Bitmap(bitmap, count) => {
+                if bitmap.bit(low as usize) {
+                    return false;
+                }
+                bitmap.set_bit(low as usize, true);
+                *count += 1;
+                true
+            }
+            Container::Runs(_) => {
+                if self.contains(low) {
+                    return false;
+                }
+                *self = Container::from_words(self.to_words()).unwrap();
+                self.insert(low)
+            }
+        }
+    }
+
+    /// Clears bit `low`, returns true if it was set before. An array container may be left
+    /// empty.
+    fn remove(&mut self, low: u16) -> bool {
+        match self {
+            Container::Array(array) => match array.binary_search(&low) {
+                Ok(pos) => {
+                    let _ = array.remove(pos);
+                    true
+                }
+                Err(_) => false,
+            },
+            Container::Bitmap(bitmap, count) => {
+                if !bitmap.bit(low as usize) {
+                    return false;
+                }
+                bitmap.set_bit(low as usize, false);
+                *count -= 1;
+                if *count <= ARRAY_MAX {
+                    *self = Container::Array(bitmap.iter().map(|i| i as u16).collect());
+                }
+                true
+            }
+            Container::Runs(_) => {
+                if !self.contains(low) {
+                    return false;
+                }
+                *self = Container::from_words(self.to_words()).unwrap();
+                self.remove(low)
+            }
+        }
+    }
+
+    /// Replaces the container with a run container if that is smaller
+    fn run_optimize(&mut self) {
+        let bitmap = GenericYabf::from_storage(self.to_words());
+        let runs: Vec<(u16, u16)> = bitmap
+            .runs()
+            .map(|r| (r.start as u16, (r.end - 1) as u16))
+            .collect();
+        let len = self.len();
+        // the sizes in bytes
+        let current = if len <= ARRAY_MAX {
+            2 * len
+        } else {
+            8 * CHUNK_WORDS
+        };
+        if 4 * runs.len() < current {
+            *self = Container::Runs(runs);
+        } else if let Container::Runs(_) = self {
+            *self = Container::from_words(bitmap.into_storage()).unwrap();
+        }
+    }
+
+    fn iter(&self) -> ContainerIter<'_> {
+        match self {
+            Container::Array(array) => ContainerIter::Array(array.iter()),
+            Container::Bitmap(bitmap, _) => ContainerIter::Bitmap(bitmap.iter()),
+            #[allow(clippy::reversed_empty_ranges)]
+            Container::Runs(runs) => ContainerIter::Runs(runs.iter(), 1..=0),
+        }
+    }
+}
+
+/// Returns the words of `op(array word, bitmap word)`, only the words holding bits of
+/// `array` are built from its bits.
+fn patch_words<F: Fn(u64, u64) -> u64>(
+    array: &[u16],
+    bitmap: &GenericYabf<Vec<u64>>,
+    op: F,
+) -> Vec<u64> {
+    let bitmap = bitmap.internals.as_slice();
+    let word = |i: usize| bitmap.get(i).copied().unwrap_or(0);
+    let mut words: Vec<u64> = (0..CHUNK_WORDS).map(|i| op(0, word(i))).collect();
+    let mut pos = 0;
+    while pos < array.len() {
+        let i = array[pos] as usize / 64;
+        let mut array_word = 0;
+        while pos < array.len() && array[pos] as usize / 64 == i {
+            array_word |= 1 << (array[pos] % 64);
+            pos += 1;
+        }
+        words[i] = op(array_word, word(i));
+    }
+    words
+}
+
+/// A compressed bit field that only stores the 2^16 bit chunks holding set bits. Dense
+/// chunks are stored as bitmaps, sparse chunks as sorted arrays and, after
+/// [`run_optimize`](RoaringYabf::run_optimize), chunks of long runs as run lists.
+///
+/// ```
+/// # use yabf::RoaringYabf;
+///
+/// let mut bf = RoaringYabf::default();
+/// bf.set_bit(3, true);
+/// bf.set_bit(1 << 40, true);
+/// assert!(bf.bit(1 << 40));
+/// assert!(!bf.bit(4));
+/// assert_eq!(bf.iter().collect::<Vec<_>>(), vec![3, 1 << 40]);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RoaringYabf {
+    // the chunks holding set bits, sorted by the high bits of their indices
+    chunks: Vec<(usize, Container)>,
+}
+
+impl RoaringYabf {
+    /// Construct an empty bit field
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the position of the chunk holding bit `n`, or where it would be inserted
+    #[inline]
+    fn find(&self, n: usize) -> Result<usize, usize> {
+        self.chunks
+            .binary_search_by_key(&(n / CHUNK_BITS), |(key, _)| *key)
+    }
+
+    /// Returns the value of bit `n`
+    #[inline]
+    pub fn bit(&self, n: usize) -> bool {
+        match self.find(n) {
+            Ok(i) => self.chunks[i].1.contains(n as u16),
+            Err(_) => false,
+        }
+    }
+
+    /// Sets bit `n` to `state`. Chunks are created when their first bit is set and dropped
+    /// when their last bit is cleared.
+    pub fn set_bit(&mut self, n: usize, state: bool) {
+        match (self.find(n), state) {
+            (Ok(i), true) => {
+                let _ = self.chunks[i].1.insert(n as u16);
+            }
+            (Ok(i), false) => {
+                let container = &mut self.chunks[i].1;
+                if container.remove(n as u16) && container.len() == 0 {
+                    let _ = self.chunks.remove(i);
+                }
+            }
+            (Err(i), true) => {
+                self.chunks
+                    .insert(i, (n / CHUNK_BITS, Container::Array(vec![n as u16])));
+            }
+            (Err(_), false) => (),
+        }
+    }
+
+    /// Returns the number of bits set to `true`
+    pub fn count_ones(&self) -> usize {
+        self.chunks.iter().map(|(_, c)| c.len()).sum()
+    }
+
+    /// Returns true if no bit is set
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Clears all bits and releases the chunks
+    #[inline]
+    pub fn clear(&mut self) {
+        self.chunks.clear()
+    }
+
+    /// Converts every chunk that is smaller as a list of runs into a run container, and every
+    /// run container that is not into an array or bitmap container.
+    ///
+    /// ```
+    /// # use yabf::RoaringYabf;
+    ///
+    /// let mut bf: RoaringYabf = (100..60_000).collect();
+    /// bf.run_optimize();
+    /// assert_eq!(bf.count_ones(), 59_900);
+    /// assert!(bf.bit(100) && !bf.bit(60_000));
+    /// ```
+    pub fn run_optimize(&mut self) {
+        for (_, container) in self.chunks.iter_mut() {
+            container.run_optimize();
+        }
+    }
+
+    /// Returns an iterator over the set bit indices, from lowest to highest
+    #[inline]
+    pub fn iter(&self) -> RoaringIterator<'_> {
+        RoaringIterator {
+            chunks: self.chunks.iter(),
+            key: 0,
+            inner: None,
+        }
+    }
+
+    /// Combines the chunks of `a` and `b` word by word with `op`, which must map `(0, 0)` to
+    /// `0`. Chunks only present in
+    /// `a` (or `b`) are kept if `keep_a` (or `keep_b`) is set.
+    fn merge(a: &Self, b: &Self, op: fn(u64, u64) -> u64, keep_a: bool, keep_b: bool) -> Self {
+        let mut chunks = Vec::new();
+        let mut a = a.chunks.iter().peekable();
+        let mut b = b.chunks.iter().peekable();
+        loop {
+            let (ka, kb) = (a.peek().map(|c| c.0), b.peek().map(|c| c.0));
+            match (ka, kb) {
+                (None, None) => break,
+                (Some(ka), Some(kb)) if ka == kb => {
+                    let (ca, cb) = (&a.next().unwrap().1, &b.next().unwrap().1);
+                    if let Some(container) = Container::combine(ca, cb, op) {
+                        chunks.push((ka, container));
+                    }
+                }
+                (Some(ka), kb) if kb.map_or(true, |kb| ka < kb) => {
+                    let chunk = a.next().unwrap();
+                    if keep_a {
+                        chunks.push(chunk.clone());
+                    }
+                }
+                _ => {
+                    let chunk = b.next().unwrap();
+                    if keep_b {
+                        chunks.push(chunk.clone());
+                    }
+                }
+            }
+        }
+        Self { chunks }
+    }
+}
+
+/// Iterator over the set bits of a [`RoaringYabf`], from lowest to highest.
+/// Created by [`RoaringYabf::iter`].
+#[derive(Clone)]
+pub struct RoaringIterator<'a> {
+    chunks: std::slice::Iter<'a, (usize, Container)>,
+    // the high bits of the current chunk
+    key: usize,
+    inner: Option<ContainerIter<'a>>,
+}
+
+/// Iterator over the low bits set in a container
+#[derive(Clone)]
+enum ContainerIter<'a> {
+    Array(std::slice::Iter<'a, u16>),
+    Bitmap(YabfIterator<'a, u64>),
+    // the remaining runs and the remainder of the current run
+    Runs(std::slice::Iter<'a, (u16, u16)>, ops::RangeInclusive<usize>),
+}
+
+impl Iterator for ContainerIter<'_> {
+    type Item = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        match self {
+            ContainerIter::Array(iter) => iter.next().map(|i| *i as usize),
+            ContainerIter::Bitmap(iter) => iter.next(),
+            ContainerIter::Runs(runs, current) => current.next().or_else(|| {
+                let (first, last) = runs.next()?;
+                *current = *first as usize + 1..=*last as usize;
+                Some(*first as usize)
+            }),
+        }
+    }
+}
+
+impl Iterator for RoaringIterator<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        loop {
+            if let Some(low) = self.inner.as_mut().and_then(|inner| inner.next()) {
+                return Some(self.key * CHUNK_BITS + low);
+            }
+            let (key, container) = self.chunks.next()?;
+            self.key = *key;
+            self.inner = Some(container.iter());
+        }
+    }
+}
+
+impl FusedIterator for RoaringIterator<'_> {}
+
+impl<'a> IntoIterator for &'a RoaringYabf {
+    type Item = usize;
+    type IntoIter = RoaringIterator<'a>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl FromIterator<usize> for RoaringYabf {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut rv = Self::default();
+        rv.extend(iter);
+        rv
+    }
+}
+
+impl Extend<usize> for RoaringYabf {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for n in iter {
+            self.set_bit(n, true);
+        }
+    }
+}
+
+/// Two bit fields are equal if the same bits are set, regardless of the containers used.
+impl PartialEq for RoaringYabf {
+    fn eq(&self, other: &Self) -> bool {
+        self.chunks.len() == other.chunks.len()
+            && self
+                .chunks
+                .iter()
+                .zip(other.chunks.iter())
+                .all(|((ka, a), (kb, b))| ka == kb && a.len() == b.len() && a.iter().eq(b.iter()))
+    }
+}
+
+impl Eq for RoaringYabf {}
+
+/// ```
+/// # use yabf::{RoaringYabf, Yabf};
+///
+/// let bf: Yabf = (0..100).step_by(7).collect();
+/// let roaring = RoaringYabf::from(&bf);
+/// assert_eq!(roaring.count_ones(), bf.count_ones());
+/// assert_eq!(Yabf::from(&roaring), bf);
+/// ```
+impl<S: Storage> From<&GenericYabf<S>> for RoaringYabf {
+    fn from(bf: &GenericYabf<S>) -> Self {
+        bf.iter().collect()
+    }
+}
+
+impl<S: Storage> From<&RoaringYabf> for GenericYabf<S> {
+    fn from(bf: &RoaringYabf) -> Self {
+        bf.iter().collect()
+    }
+}
+
+// the chunks are merged into a new bit field, even for `a op= &b`
+impl_binary_op!(
+    assign [] RoaringYabf,
+    RoaringYabf,
+    BitOr,
+    bitor,
+    BitOrAssign,
+    bitor_assign,
+    |a, b| RoaringYabf::merge(a, b, |x, y| x | y, true, true)
+);
+impl_binary_op!(
+    assign [] RoaringYabf,
+    RoaringYabf,
+    BitAnd,
+    bitand,
+    BitAndAssign,
+    bitand_assign,
+    |a, b| RoaringYabf::merge(a, b, |x, y| x & y, false, false)
+);
+impl_binary_op!(
+    assign [] RoaringYabf,
+    RoaringYabf,
+    BitXor,
+    bitxor,
+    BitXorAssign,
+    bitxor_assign,
+    |a, b| RoaringYabf::merge(a, b, |x, y| x ^ y, true, true)
+);
+impl_binary_op!(
+    assign [] RoaringYabf,
+    RoaringYabf,
+    Sub,
+    sub,
+    SubAssign,
+    sub_assign,
+    |a, b| RoaringYabf::merge(a, b, |x, y| x & !y, true, false)
+);
+
+#[cfg(test)]
+mod test {
+    use super::{Container, RoaringYabf, ARRAY_MAX};
+    use rand::{Rng, SeedableRng};
+    use std::collections::BTreeSet;
+
+    /// A mix of sparse, dense and run heavy chunks
+    fn sample(seed: u64) -> (RoaringYabf, BTreeSet<usize>) {
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(seed);
+        let mut set = BTreeSet::new();
+        for _ in 0..500 {
+            let _ = set.insert(rng.gen_range(0..1 << 20));
+        }
+        for _ in 0..6000 {
+            let _ = set.insert(rng.gen_range(1 << 21..(1 << 21) + (1 << 16)));
+        }
+        let start = rng.gen_range(1 << 22..1 << 23);
+        set.extend(start..start + 70_000);
+        let _ = set.insert(usize::MAX);
+        (set.iter().copied().collect(), set)
+    }
+
+    fn check(bf: &RoaringYabf, set: &BTreeSet<usize>) {
+        assert_eq!(bf.count_ones(), set.len());
+        assert!(bf.iter().eq(set.iter().copied()));
+        for n in set.iter().take(100) {
+            assert!(bf.bit(*n));
+            assert_eq!(bf.bit(n ^ 0x5555), set.contains(&(n ^ 0x5555)));
+        }
+    }
+
+    #[test]
+    fn test_set_bit() {
+        let (mut bf, mut set) = sample(1);
+        check(&bf, &set);
+        assert!(bf
+            .chunks
+            .iter()
+            .any(|(_, c)| matches!(c, Container::Bitmap(..))));
+        let mut optimized = bf.clone();
+        optimized.run_optimize();
+        assert!(optimized
+            .chunks
+            .iter()
+            .any(|(_, c)| matches!(c, Container::Runs(..))));
+        check(&optimized, &set);
+        assert_eq!(optimized, bf);
+
+        // clearing bits converts bitmaps back to arrays and drops empty chunks
+        let removed: Vec<usize> = set.iter().copied().step_by(2).collect();
+        for n in removed.iter() {
+            bf.set_bit(*n, false);
+            optimized.set_bit(*n, false);
+            let _ = set.remove(n);
+        }
+        check(&bf, &set);
+        check(&optimized, &set);
+        for n in set.clone().iter() {
+            bf.set_bit(*n, false);
+        }
+        assert!(bf.is_empty());
+        assert_eq!(bf, RoaringYabf::new());
+    }
+
+    #[test]
+    fn test_container_switch() {
+        let mut bf = RoaringYabf::new();
+        for n in 0..=ARRAY_MAX {
+            bf.set_bit(n * 2, true);
+        }
+        assert!(matches!(bf.chunks[0].1, Container::Bitmap(..)));
+        bf.set_bit(0, false);
+        assert!(matches!(bf.chunks[0].1, Container::Array(..)));
+        assert_eq!(bf.count_ones(), ARRAY_MAX);
+        bf.set_bit(1, true);
+        assert!(matches!(bf.chunks[0].1, Container::Bitmap(..)));
+        bf.set_bit(1, false);
+        assert!(matches!(bf.chunks[0].1, Container::Array(..)));
+
+        // conversions pick the container by the number of bits in each chunk
+        let dense = crate::Yabf::from_ranges(vec![0..ARRAY_MAX, 65_536..65_537 + ARRAY_MAX]);
+        let bf = RoaringYabf::from(&dense);
+        assert!(matches!(bf.chunks[0].1, Container::Array(..)));
+        assert!(matches!(bf.chunks[1].1, Container::Bitmap(..)));
+        assert_eq!(crate::Yabf::from(&bf), dense);
+        // a run container switches back when a bit is set or cleared
+        let mut runs = bf.clone();
+        runs.run_optimize();
+        assert!(matches!(runs.chunks[1].1, Container::Runs(..)));
+        runs.set_bit(65_536, false);
+        assert!(matches!(runs.chunks[1].1, Container::Array(..)));
+        runs.set_bit(65_536, true);
+        assert!(matches!(runs.chunks[1].1, Container::Bitmap(..)));
+        assert_eq!(runs, bf);
+    }
+
+    #[test]
+    fn test_set_ops() {
+        let (mut a, sa) = sample(2);
+        let (b, sb) = sample(3);
+        a.run_optimize();
+        check(&(&a | &b), &(&sa | &sb));
+        check(&(&a & &b), &(&sa & &sb));
+        check(&(&a ^ &b), &(&sa ^ &sb));
+        check(&(&a - &b), &(&sa - &sb));
+        let mut c = a.clone();
+        c -= &a;
+        assert!(c.is_empty());
+        c |= &b;
+        assert_eq!(c, b);
+        assert_eq!(c ^ &b, RoaringYabf::new());
+    }
+
+    #[test]
+    fn test_combine_containers() {
+        // arrays, an array/array union that needs a bitmap and a bitmap
+        let sets: Vec<BTreeSet<usize>> = vec![
+            vec![1, 70, 4000, 65_535].into_iter().collect(),
+            (0..6000).step_by(2).collect(),
+            (0..9000).step_by(3).collect(),
+            (0..65_536).step_by(7).collect(),
+        ];
+        for sa in sets.iter() {
+            for sb in sets.iter() {
+                let a: RoaringYabf = sa.iter().copied().collect();
+                let b: RoaringYabf = sb.iter().copied().collect();
+                let results = [
+                    (&a | &b, sa | sb),
+                    (&a & &b, sa & sb),
+                    (&a ^ &b, sa ^ sb),
+                    (&a - &b, sa - sb),
+                ];
+                for (bf, set) in results.iter() {
+                    check(bf, set);
+                    if let Some((_, container)) = bf.chunks.first() {
+                        let is_array = matches!(container, Container::Array(..));
+                        assert_eq!(is_array, set.len() <= ARRAY_MAX);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_yabf_conversion() {
+        let bf: crate::Yabf = (0..200_000).step_by(3).collect();
+        let roaring = RoaringYabf::from(&bf);
+        assert_eq!(roaring.count_ones(), bf.count_ones());
+        assert_eq!(crate::Yabf::from(&roaring), bf);
+    }
+}
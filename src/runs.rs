@@ -0,0 +1,153 @@
+//! Maximal runs of set (or unset) bits as ranges.
+
+use crate::search::{next_one, next_zero};
+use crate::{to_range, GenericYabf, Storage, Word};
+use std::iter::FusedIterator;
+use std::ops::{Range, RangeBounds};
+
+/// Iterator over the maximal runs of set bits, or of unset bits, as half open ranges.
+/// Created by [`GenericYabf::runs`] and [`GenericYabf::zero_runs`].
+#[derive(Clone)]
+pub struct YabfRunsIterator<'s, W = u32> {
+    words: &'s [W],
+    // the next bit to examine
+    pos: usize,
+    // the end of the examined range (exclusive)
+    end: usize,
+    // if true, runs of set bits are reported, otherwise runs of unset bits
+    ones: bool,
+}
+
+impl<S: Storage> GenericYabf<S> {
+    /// Returns an iterator over the maximal runs of set bits, from lowest to highest.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let bf = Yabf::from_ranges(vec![3..5, 30..70]);
+    /// assert_eq!(bf.runs().collect::<Vec<_>>(), vec![3..5, 30..70]);
+    /// ```
+    #[inline]
+    pub fn runs(&self) -> YabfRunsIterator<'_, S::Word> {
+        let words = self.internals.as_slice();
+        YabfRunsIterator {
+            words,
+            pos: 0,
+            end: words.len() * S::Word::BITS,
+            ones: true,
+        }
+    }
+
+    /// Returns an iterator over the maximal runs of unset bits within `range`, from lowest
+    /// to highest. The runs are clipped to `range`, everything beyond `internal_len()` is
+    /// treated as zero.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let bf = Yabf::from_ranges(vec![3..5, 30..70]);
+    /// assert_eq!(bf.zero_runs(0..100).collect::<Vec<_>>(), vec![0..3, 5..30, 70..100]);
+    /// assert_eq!(bf.zero_runs(4..40).collect::<Vec<_>>(), vec![5..30]);
+    /// ```
+    #[inline]
+    pub fn zero_runs<R: RangeBounds<usize>>(&self, range: R) -> YabfRunsIterator<'_, S::Word> {
+        let range = to_range(&range);
+        YabfRunsIterator {
+            words: self.internals.as_slice(),
+            pos: range.start,
+            end: range.end,
+            ones: false,
+        }
+    }
+
+    /// Construct a bit field with every bit within the given ranges set.
+    /// The ranges may overlap and need not be sorted.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let bf = Yabf::from_ranges(vec![3..5, 1..2]);
+    /// assert_eq!(bf.into_iter().collect::<Vec<_>>(), vec![1, 3, 4]);
+    /// ```
+    pub fn from_ranges<I: IntoIterator<Item = Range<usize>>>(ranges: I) -> Self {
+        let mut rv = Self::with_capacity(0);
+        for range in ranges {
+            rv.set_range(range);
+        }
+        rv
+    }
+}
+
+impl<'s, W: Word> Iterator for YabfRunsIterator<'s, W> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Range<usize>> {
+        if self.pos >= self.end {
+            return None;
+        }
+        let (start, stop) = if self.ones {
+            let start = next_one(self.words, self.pos)?;
+            (start, next_zero(self.words, start))
+        } else {
+            let start = next_zero(self.words, self.pos);
+            (start, next_one(self.words, start).unwrap_or(usize::MAX))
+        };
+        if start >= self.end {
+            self.pos = self.end;
+            return None;
+        }
+        let stop = stop.min(self.end);
+        self.pos = stop;
+        Some(start..stop)
+    }
+}
+
+impl<'s, W: Word> FusedIterator for YabfRunsIterator<'s, W> {}
+
+#[cfg(test)]
+mod test {
+    use crate::GenericYabf;
+
+    fn check<S: crate::Storage + Default>() {
+        let ranges = vec![0..1, 3..5, 7..8, 8..9, 31..33, 63..200, 300..301, 511..512];
+        let bf = GenericYabf::<S>::from_ranges(ranges.clone());
+        let merged = vec![0..1, 3..5, 7..9, 31..33, 63..200, 300..301, 511..512];
+        assert_eq!(bf.runs().collect::<Vec<_>>(), merged);
+        for r in ranges.iter() {
+            for b in r.clone() {
+                assert!(bf.bit(b));
+            }
+        }
+        assert_eq!(bf.count_ones(), 1 + 2 + 2 + 2 + 137 + 1 + 1);
+        assert_eq!(
+            bf.zero_runs(..600).collect::<Vec<_>>(),
+            vec![1..3, 5..7, 9..31, 33..63, 200..300, 301..511, 512..600]
+        );
+        assert_eq!(
+            bf.zero_runs(100..310).collect::<Vec<_>>(),
+            vec![200..300, 301..310]
+        );
+        assert_eq!(bf.zero_runs(100..150).count(), 0);
+        assert_eq!(
+            bf.zero_runs(510..).collect::<Vec<_>>(),
+            vec![510..511, 512..usize::MAX]
+        );
+        assert_eq!(GenericYabf::<S>::default().runs().count(), 0);
+        assert_eq!(
+            GenericYabf::<S>::default()
+                .zero_runs(5..10)
+                .collect::<Vec<_>>(),
+            vec![5..10]
+        );
+    }
+
+    #[test]
+    fn test_runs() {
+        check::<Vec<u8>>();
+        check::<Vec<u32>>();
+        check::<Vec<u64>>();
+        check::<Vec<u128>>();
+        #[cfg(feature = "smallvec")]
+        check::<smallvec::SmallVec<[u32; 4]>>();
+    }
+}
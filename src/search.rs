@@ -0,0 +1,212 @@
+//! Neighbour search: finding the closest set or unset bit from a position with word scans.
+//! Together with `set_bit` this allows a bit field to be used as an ordered set of indices.
+
+use crate::word::low_mask;
+use crate::{GenericYabf, Storage, Word};
+
+/// Returns the position of the first set bit at or above `from`.
+pub(crate) fn next_one<W: Word>(words: &[W], from: usize) -> Option<usize> {
+    let mut index = from / W::BITS;
+    let mut word = *words.get(index)? & !low_mask::<W>(from % W::BITS);
+    while word == W::ZERO {
+        index += 1;
+        word = *words.get(index)?;
+    }
+    Some(index * W::BITS + word.trailing_zeros() as usize)
+}
+
+/// Returns the position of the first unset bit at or above `from`. Bits beyond the end of
+/// `words` are unset, so this may return `words.len() * W::BITS` (or `from` itself).
+pub(crate) fn next_zero<W: Word>(words: &[W], from: usize) -> usize {
+    let mut index = from / W::BITS;
+    let mut word = match words.get(index) {
+        Some(word) => !*word & !low_mask::<W>(from % W::BITS),
+        None => return from,
+    };
+    while word == W::ZERO {
+        index += 1;
+        word = match words.get(index) {
+            Some(word) => !*word,
+            None => return index * W::BITS,
+        };
+    }
+    index * W::BITS + word.trailing_zeros() as usize
+}
+
+/// Returns the position of the last set bit at or below `from`.
+pub(crate) fn prev_one<W: Word>(words: &[W], from: usize) -> Option<usize> {
+    let mut index = from / W::BITS;
+    let mut word = if index >= words.len() {
+        index = words.len().checked_sub(1)?;
+        words[index]
+    } else {
+        words[index] & low_mask::<W>(from % W::BITS + 1)
+    };
+    while word == W::ZERO {
+        index = index.checked_sub(1)?;
+        word = words[index];
+    }
+    Some(index * W::BITS + W::BITS - 1 - word.leading_zeros() as usize)
+}
+
+/// Returns the position of the last unset bit at or below `from`.
+pub(crate) fn prev_zero<W: Word>(words: &[W], from: usize) -> Option<usize> {
+    let mut index = from / W::BITS;
+    if index >= words.len() {
+        return Some(from);
+    }
+    let mut word = !words[index] & low_mask::<W>(from % W::BITS + 1);
+    while word == W::ZERO {
+        index = index.checked_sub(1)?;
+        word = !words[index];
+    }
+    Some(index * W::BITS + W::BITS - 1 - word.leading_zeros() as usize)
+}
+
+impl<S: Storage> GenericYabf<S> {
+    /// Returns the position of the lowest set bit, or `None` if no bit is set.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let mut bf = Yabf::default();
+    /// assert_eq!(bf.first_set(), None);
+    /// bf.set_bit(300, true);
+    /// bf.set_bit(30, true);
+    /// assert_eq!(bf.first_set(), Some(30));
+    /// ```
+    #[inline]
+    pub fn first_set(&self) -> Option<usize> {
+        next_one(self.internals.as_slice(), 0)
+    }
+
+    /// Returns the position of the highest set bit, or `None` if no bit is set.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let mut bf = Yabf::default();
+    /// assert_eq!(bf.last_set(), None);
+    /// bf.set_bit(300, true);
+    /// bf.set_bit(30, true);
+    /// assert_eq!(bf.last_set(), Some(300));
+    /// ```
+    #[inline]
+    pub fn last_set(&self) -> Option<usize> {
+        prev_one(self.internals.as_slice(), usize::MAX)
+    }
+
+    /// Returns the position of the first set bit at or above bit `n`.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let mut bf = Yabf::default();
+    /// bf.set_bit(30, true);
+    /// bf.set_bit(300, true);
+    /// assert_eq!(bf.next_set(30), Some(30));
+    /// assert_eq!(bf.next_set(31), Some(300));
+    /// assert_eq!(bf.next_set(301), None);
+    /// ```
+    #[inline]
+    pub fn next_set(&self, n: usize) -> Option<usize> {
+        next_one(self.internals.as_slice(), n)
+    }
+
+    /// Returns the position of the last set bit at or below bit `n`.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let mut bf = Yabf::default();
+    /// bf.set_bit(30, true);
+    /// bf.set_bit(300, true);
+    /// assert_eq!(bf.prev_set(300), Some(300));
+    /// assert_eq!(bf.prev_set(299), Some(30));
+    /// assert_eq!(bf.prev_set(29), None);
+    /// ```
+    #[inline]
+    pub fn prev_set(&self, n: usize) -> Option<usize> {
+        prev_one(self.internals.as_slice(), n)
+    }
+
+    /// Returns the position of the first unset bit at or above bit `n`. All bits beyond the
+    /// internal storage are unset, so there is always one.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let mut bf = Yabf::default();
+    /// bf.set_range(10..20);
+    /// assert_eq!(bf.next_unset(5), 5);
+    /// assert_eq!(bf.next_unset(10), 20);
+    /// ```
+    #[inline]
+    pub fn next_unset(&self, n: usize) -> usize {
+        next_zero(self.internals.as_slice(), n)
+    }
+
+    /// Returns the position of the last unset bit at or below bit `n`, or `None` if all the
+    /// bits `0..=n` are set.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let mut bf = Yabf::default();
+    /// bf.set_range(0..20);
+    /// assert_eq!(bf.prev_unset(25), Some(25));
+    /// assert_eq!(bf.prev_unset(19), None);
+    /// ```
+    #[inline]
+    pub fn prev_unset(&self, n: usize) -> Option<usize> {
+        prev_zero(self.internals.as_slice(), n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::GenericYabf;
+
+    fn check<S: crate::Storage + Default>() {
+        let bits = [1_usize, 7, 8, 9, 31, 32, 33, 63, 64, 127, 128, 129, 500];
+        let mut bf = GenericYabf::<S>::default();
+        assert_eq!(bf.first_set(), None);
+        assert_eq!(bf.last_set(), None);
+        assert_eq!(bf.prev_set(100), None);
+        assert_eq!(bf.next_unset(100), 100);
+        assert_eq!(bf.prev_unset(100), Some(100));
+        for b in bits.iter() {
+            bf.set_bit(*b, true);
+        }
+        // trailing zero words
+        bf.set_bit(1000, true);
+        bf.set_bit(1000, false);
+        assert_eq!(bf.first_set(), Some(1));
+        assert_eq!(bf.last_set(), Some(500));
+        for n in 0..1100 {
+            let next = bits.iter().copied().find(|b| *b >= n);
+            let prev = bits.iter().rev().copied().find(|b| *b <= n);
+            let next_unset = (n..).find(|b| !bits.contains(b)).unwrap();
+            let prev_unset = (0..=n).rev().find(|b| !bits.contains(b));
+            assert_eq!(bf.next_set(n), next, "next_set({})", n);
+            assert_eq!(bf.prev_set(n), prev, "prev_set({})", n);
+            assert_eq!(bf.next_unset(n), next_unset, "next_unset({})", n);
+            assert_eq!(bf.prev_unset(n), prev_unset, "prev_unset({})", n);
+        }
+        assert_eq!(bf.prev_set(usize::MAX), Some(500));
+        assert_eq!(bf.prev_unset(usize::MAX), Some(usize::MAX));
+        assert_eq!(bf.prev_unset(1), Some(0));
+        bf.set_bit(0, true);
+        assert_eq!(bf.prev_unset(1), None);
+    }
+
+    #[test]
+    fn test_neighbours() {
+        check::<Vec<u8>>();
+        check::<Vec<u32>>();
+        check::<Vec<u64>>();
+        check::<Vec<u128>>();
+        #[cfg(feature = "smallvec")]
+        check::<smallvec::SmallVec<[u32; 4]>>();
+    }
+}
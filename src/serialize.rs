@@ -0,0 +1,266 @@
+//! Serde support, enabled by the `serde` feature.
+//!
+//! Human readable formats (e.g. JSON) store the set bits as a list where each element is
+//! either a single index or a `[start, end]` pair describing the half open range
+//! `start..end`. Binary formats store the bits as a sequence of little endian ordered `u64`
+//! words without trailing zero words.
+//!
+//! Human readable runs ending beyond [`MAX_BIT_LEN`](crate::MAX_BIT_LEN) are rejected, as
+//! a short list could otherwise describe an arbitrarily large bit field.
+//!
+//! Neither encoding depends on the storage or word type, so a bit field serialized as a
+//! [`Yabf`](crate::Yabf) can be deserialized as a [`SmallYabf`](crate::SmallYabf) and
+//! vice versa.
+
+use crate::{GenericYabf, Storage, MAX_BIT_LEN};
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeSeq, SerializeTuple, Serializer};
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+/// An upper bound on the number of elements pre-allocated from an untrusted size hint
+const MAX_PREALLOCATION: usize = 4096;
+
+/// ```
+/// # use yabf::Yabf;
+///
+/// let bf = Yabf::from_ranges(vec![1..2, 5..9]);
+/// assert_eq!(serde_json::to_string(&bf).unwrap(), "[1,[5,9]]");
+/// ```
+impl<S: Storage> Serialize for GenericYabf<S> {
+    fn serialize<Z: Serializer>(&self, serializer: Z) -> Result<Z::Ok, Z::Error> {
+        if serializer.is_human_readable() {
+            let mut seq = serializer.serialize_seq(None)?;
+            for run in self.runs() {
+                seq.serialize_element(&Run(run))?;
+            }
+            seq.end()
+        } else {
+            let mut words = self.to_u64_words();
+            while words.last() == Some(&0) {
+                let _ = words.pop();
+            }
+            let mut seq = serializer.serialize_seq(Some(words.len()))?;
+            for w in words.iter() {
+                seq.serialize_element(w)?;
+            }
+            seq.end()
+        }
+    }
+}
+
+/// ```
+/// # use yabf::Yabf;
+///
+/// let bf: Yabf = serde_json::from_str("[1,[5,9]]").unwrap();
+/// assert_eq!(bf.iter().collect::<Vec<_>>(), vec![1, 5, 6, 7, 8]);
+/// ```
+impl<'de, S: Storage> Deserialize<'de> for GenericYabf<S> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_seq(RunsVisitor(PhantomData))
+        } else {
+            deserializer.deserialize_seq(WordsVisitor(PhantomData))
+        }
+    }
+}
+
+/// A run of set bits, serialized as a single index if the run is one bit long, otherwise
+/// as a `[start, end]` pair.
+struct Run(Range<usize>);
+
+impl Serialize for Run {
+    fn serialize<Z: Serializer>(&self, serializer: Z) -> Result<Z::Ok, Z::Error> {
+        if self.0.len() == 1 {
+            serializer.serialize_u64(self.0.start as u64)
+        } else {
+            let mut tuple = serializer.serialize_tuple(2)?;
+            tuple.serialize_element(&(self.0.start as u64))?;
+            tuple.serialize_element(&(self.0.end as u64))?;
+            tuple.end()
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Run {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(RunVisitor)
+    }
+}
+
+struct RunVisitor;
+
+impl<'de> Visitor<'de> for RunVisitor {
+    type Value = Run;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a bit index or a [start, end] pair")
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Run, E> {
+        let start = to_index(v)?;
+        let end = start
+            .checked_add(1)
+            .ok_or_else(|| E::custom("bit index out of range"))?;
+        Ok(Run(start..end))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Run, E> {
+        if v < 0 {
+            return Err(E::invalid_value(de::Unexpected::Signed(v), &self));
+        }
+        self.visit_u64(v as u64)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Run, A::Error> {
+        let start: u64 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let end: u64 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        if seq.next_element::<de::IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(3, &self));
+        }
+        if start >= end {
+            return Err(de::Error::custom(format!(
+                "empty or reversed range [{}, {}]",
+                start, end
+            )));
+        }
+        Ok(Run(to_index(start)?..to_index(end)?))
+    }
+}
+
+/// Converts a deserialized `u64` into a bit index
+fn to_index<E: de::Error>(v: u64) -> Result<usize, E> {
+    if v > usize::MAX as u64 {
+        Err(E::custom("bit index out of range"))
+    } else {
+        Ok(v as usize)
+    }
+}
+
+/// Deserializes the human readable list of runs
+struct RunsVisitor<S>(PhantomData<S>);
+
+impl<'de, S: Storage> Visitor<'de> for RunsVisitor<S> {
+    type Value = GenericYabf<S>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a list of bit indices and [start, end] pairs")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut rv = GenericYabf::with_capacity(0);
+        while let Some(Run(range)) = seq.next_element()? {
+            if range.end as u64 > MAX_BIT_LEN {
+                return Err(de::Error::custom(format!(
+                    "bit index {} exceeds the limit {}",
+                    range.end - 1,
+                    MAX_BIT_LEN
+                )));
+            }
+            rv.set_range(range);
+        }
+        Ok(rv)
+    }
+}
+
+/// Deserializes the binary sequence of `u64` words
+struct WordsVisitor<S>(PhantomData<S>);
+
+impl<'de, S: Storage> Visitor<'de> for WordsVisitor<S> {
+    type Value = GenericYabf<S>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a sequence of u64 words")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let hint = seq.size_hint().unwrap_or(0).min(MAX_PREALLOCATION);
+        let mut words = Vec::with_capacity(hint);
+        while let Some(w) = seq.next_element::<u64>()? {
+            words.push(w);
+        }
+        Ok(GenericYabf::from_u64_words(&words))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::GenericYabf;
+
+    fn sample<S: crate::Storage + Default>() -> GenericYabf<S> {
+        let mut bf = GenericYabf::<S>::from_ranges(vec![3..4, 10..20, 63..65, 1000..1001]);
+        bf.set_bit(5000, true);
+        bf.set_bit(5000, false);
+        bf
+    }
+
+    fn check<S: crate::Storage + Default>() {
+        let bf = sample::<S>();
+
+        let json = serde_json::to_string(&bf).unwrap();
+        assert_eq!(json, "[3,[10,20],[63,65],1000]");
+        let back: GenericYabf<S> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, bf);
+
+        let bytes = bincode::serialize(&bf).unwrap();
+        // length prefix and 16 words
+        assert_eq!(bytes.len(), 8 + 16 * 8);
+        let back: GenericYabf<S> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(back, bf);
+
+        let empty = GenericYabf::<S>::default();
+        assert_eq!(serde_json::to_string(&empty).unwrap(), "[]");
+        let back: GenericYabf<S> =
+            bincode::deserialize(&bincode::serialize(&empty).unwrap()).unwrap();
+        assert!(back.is_empty());
+    }
+
+    #[test]
+    fn test_serde() {
+        check::<Vec<u8>>();
+        check::<Vec<u32>>();
+        check::<Vec<u64>>();
+        check::<Vec<u128>>();
+        #[cfg(feature = "smallvec")]
+        check::<smallvec::SmallVec<[u32; 4]>>();
+    }
+
+    #[cfg(feature = "smallvec")]
+    #[test]
+    fn test_cross_storage() {
+        let bf = sample::<Vec<u32>>();
+        let json = serde_json::to_string(&bf).unwrap();
+        let small: crate::SmallYabf = serde_json::from_str(&json).unwrap();
+        assert_eq!(small, bf);
+        let bytes = bincode::serialize(&small).unwrap();
+        let wide: GenericYabf<Vec<u64>> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(
+            wide.iter().collect::<Vec<_>>(),
+            bf.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert!(serde_json::from_str::<crate::Yabf>("[[5,5]]").is_err());
+        assert!(serde_json::from_str::<crate::Yabf>("[[7,5]]").is_err());
+        assert!(serde_json::from_str::<crate::Yabf>("[[1,2,3]]").is_err());
+        assert!(serde_json::from_str::<crate::Yabf>("[[1]]").is_err());
+        assert!(serde_json::from_str::<crate::Yabf>("[-1]").is_err());
+        assert!(serde_json::from_str::<crate::Yabf>("[\"1\"]").is_err());
+        assert!(serde_json::from_str::<crate::Yabf>("{}").is_err());
+        // runs beyond the limit are rejected before anything is allocated
+        assert!(serde_json::from_str::<crate::Yabf>("[[0,18446744073709551615]]").is_err());
+        assert!(serde_json::from_str::<crate::Yabf>("[1000000000000]").is_err());
+        let max = crate::MAX_BIT_LEN;
+        assert!(serde_json::from_str::<crate::Yabf>(&format!("[{}]", max)).is_err());
+        // overlapping and unordered runs are accepted
+        let bf: crate::Yabf = serde_json::from_str("[[5,9],2,[1,6]]").unwrap();
+        assert_eq!(bf.runs().collect::<Vec<_>>(), vec![1..9]);
+    }
+}
@@ -0,0 +1,264 @@
+//! Similarity and distance measures between bit fields, treated as sets of indices.
+//!
+//! Every measure is computed from word popcounts in a single pass, without allocation.
+
+use crate::bitops::word_pairs;
+use crate::{GenericYabf, Storage, Word};
+
+/// A similarity measure between two sets `A` and `B`, used by [`GenericYabf::similarities`].
+///
+/// All measures are in the range `0.0..=1.0`, where two empty sets count as identical
+/// (`1.0`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Similarity {
+    /// `|A ∩ B| / |A ∪ B|`
+    Jaccard,
+    /// `2|A ∩ B| / (|A| + |B|)`
+    Dice,
+    /// `|A ∩ B| / sqrt(|A| |B|)`
+    Cosine,
+    /// `|A ∩ B| / (|A ∩ B| + alpha |A - B| + beta |B - A|)`, `alpha` and `beta` must not be
+    /// negative. Jaccard is Tversky with `alpha = beta = 1` and Dice with `alpha = beta = 0.5`.
+    Tversky {
+        /// The weight of the bits only set in `A`
+        alpha: f64,
+        /// The weight of the bits only set in `B`
+        beta: f64,
+    },
+}
+
+impl Similarity {
+    /// Computes the measure from `|A|`, `|B|` and `|A ∩ B|`.
+    fn score(self, a: usize, b: usize, both: usize) -> f64 {
+        if a == 0 && b == 0 {
+            return 1.0;
+        }
+        let (a, b, both) = (a as f64, b as f64, both as f64);
+        match self {
+            Similarity::Jaccard => both / (a + b - both),
+            Similarity::Dice => 2.0 * both / (a + b),
+            Similarity::Cosine => {
+                if a == 0.0 || b == 0.0 {
+                    0.0
+                } else {
+                    both / (a * b).sqrt()
+                }
+            }
+            Similarity::Tversky { alpha, beta } => {
+                let denominator = both + alpha * (a - both) + beta * (b - both);
+                if denominator == 0.0 {
+                    // nothing in common, and at least one of the sets is not empty
+                    0.0
+                } else {
+                    both / denominator
+                }
+            }
+        }
+    }
+}
+
+impl<S: Storage> GenericYabf<S> {
+    /// Returns `|self|`, `|other|` and `|self ∩ other|`
+    fn popcounts<T: Storage<Word = S::Word>>(
+        &self,
+        other: &GenericYabf<T>,
+    ) -> (usize, usize, usize) {
+        word_pairs(self.internals.as_slice(), other.internals.as_slice()).fold(
+            (0, 0, 0),
+            |(a, b, both), (wa, wb)| {
+                (
+                    a + wa.count_ones() as usize,
+                    b + wb.count_ones() as usize,
+                    both + (wa & wb).count_ones() as usize,
+                )
+            },
+        )
+    }
+
+    /// Returns the number of bits that differ between `self` and `other`.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let a = Yabf::from_ranges(vec![0..10]);
+    /// let b = Yabf::from_ranges(vec![5..100]);
+    /// assert_eq!(a.hamming_distance(&b), 5 + 90);
+    /// ```
+    pub fn hamming_distance<T: Storage<Word = S::Word>>(&self, other: &GenericYabf<T>) -> usize {
+        word_pairs(self.internals.as_slice(), other.internals.as_slice())
+            .map(|(a, b)| (a ^ b).count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns the [`Similarity`] measure between `self` and `other`.
+    ///
+    /// ```
+    /// # use yabf::{Similarity, Yabf};
+    ///
+    /// let a = Yabf::from_ranges(vec![0..10]);
+    /// let b = Yabf::from_ranges(vec![5..20]);
+    /// assert_eq!(a.similarity(&b, Similarity::Jaccard), 0.25);
+    /// assert_eq!(a.similarity(&b, Similarity::Dice), 0.4);
+    /// ```
+    pub fn similarity<T: Storage<Word = S::Word>>(
+        &self,
+        other: &GenericYabf<T>,
+        measure: Similarity,
+    ) -> f64 {
+        let (a, b, both) = self.popcounts(other);
+        measure.score(a, b, both)
+    }
+
+    /// Returns the Jaccard index `|A ∩ B| / |A ∪ B|`, `1.0` if both bit fields are empty.
+    #[inline]
+    pub fn jaccard<T: Storage<Word = S::Word>>(&self, other: &GenericYabf<T>) -> f64 {
+        self.similarity(other, Similarity::Jaccard)
+    }
+
+    /// Returns the Sørensen–Dice coefficient `2|A ∩ B| / (|A| + |B|)`, `1.0` if both bit
+    /// fields are empty.
+    #[inline]
+    pub fn dice<T: Storage<Word = S::Word>>(&self, other: &GenericYabf<T>) -> f64 {
+        self.similarity(other, Similarity::Dice)
+    }
+
+    /// Returns the cosine similarity `|A ∩ B| / sqrt(|A| |B|)`, `1.0` if both bit fields are
+    /// empty and `0.0` if only one of them is.
+    #[inline]
+    pub fn cosine<T: Storage<Word = S::Word>>(&self, other: &GenericYabf<T>) -> f64 {
+        self.similarity(other, Similarity::Cosine)
+    }
+
+    /// Returns the Tversky index `|A ∩ B| / (|A ∩ B| + alpha |A - B| + beta |B - A|)`, see
+    /// [`Similarity::Tversky`].
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let a = Yabf::from_ranges(vec![0..10]);
+    /// let b = Yabf::from_ranges(vec![5..20]);
+    /// assert_eq!(a.tversky(&b, 1.0, 1.0), a.jaccard(&b));
+    /// assert_eq!(a.tversky(&b, 0.5, 0.5), a.dice(&b));
+    /// // how much of `a` is covered by `b`
+    /// assert_eq!(a.tversky(&b, 1.0, 0.0), 0.5);
+    /// ```
+    #[inline]
+    pub fn tversky<T: Storage<Word = S::Word>>(
+        &self,
+        other: &GenericYabf<T>,
+        alpha: f64,
+        beta: f64,
+    ) -> f64 {
+        self.similarity(other, Similarity::Tversky { alpha, beta })
+    }
+
+    /// Returns an iterator over the [`Similarity`] measures between `self` and each of
+    /// `others`. The popcount of `self` is computed once.
+    ///
+    /// ```
+    /// # use yabf::{Similarity, Yabf};
+    ///
+    /// let query = Yabf::from_ranges(vec![0..10]);
+    /// let corpus = vec![
+    ///     Yabf::from_ranges(vec![0..10]),
+    ///     Yabf::from_ranges(vec![5..20]),
+    ///     Yabf::from_ranges(vec![100..110]),
+    /// ];
+    /// let scores: Vec<f64> = query.similarities(&corpus, Similarity::Jaccard).collect();
+    /// assert_eq!(scores, vec![1.0, 0.25, 0.0]);
+    /// ```
+    pub fn similarities<'a, T, I>(
+        &'a self,
+        others: I,
+        measure: Similarity,
+    ) -> impl Iterator<Item = f64> + 'a
+    where
+        T: Storage<Word = S::Word> + 'a,
+        I: IntoIterator<Item = &'a GenericYabf<T>>,
+        I::IntoIter: 'a,
+    {
+        let query = self.internals.as_slice();
+        let a = self.count_ones();
+        others.into_iter().map(move |other| {
+            let (b, both) =
+                other
+                    .internals
+                    .as_slice()
+                    .iter()
+                    .enumerate()
+                    .fold((0, 0), |(b, both), (i, w)| {
+                        let q = query.get(i).copied().unwrap_or(S::Word::ZERO);
+                        (
+                            b + w.count_ones() as usize,
+                            both + (q & *w).count_ones() as usize,
+                        )
+                    });
+            measure.score(a, b, both)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{GenericYabf, Similarity};
+
+    fn check<S: crate::Storage + Default>() {
+        let a: GenericYabf<S> = (0..300).step_by(2).collect();
+        let b: GenericYabf<S> = (0..100).step_by(3).collect();
+        let (na, nb) = (a.count_ones() as f64, b.count_ones() as f64);
+        let both = a.intersection_count(&b) as f64;
+        let union = a.union_count(&b) as f64;
+
+        assert_eq!(a.hamming_distance(&b), (&a ^ &b).count_ones());
+        assert_eq!(a.jaccard(&b), both / union);
+        assert_eq!(a.dice(&b), 2.0 * both / (na + nb));
+        assert_eq!(a.cosine(&b), both / (na * nb).sqrt());
+        assert_eq!(a.jaccard(&b), b.jaccard(&a));
+        assert_eq!(a.tversky(&b, 1.0, 0.0), both / na);
+        assert_eq!(a.tversky(&b, 0.0, 1.0), both / nb);
+
+        let empty = GenericYabf::<S>::default();
+        assert_eq!(a.hamming_distance(&a), 0);
+        assert_eq!(empty.jaccard(&empty), 1.0);
+        assert_eq!(empty.cosine(&empty), 1.0);
+        assert_eq!(empty.cosine(&a), 0.0);
+        assert_eq!(a.jaccard(&empty), 0.0);
+        assert_eq!(a.dice(&a), 1.0);
+        assert_eq!(a.tversky(&b, 0.0, 0.0), 1.0);
+        assert_eq!(empty.tversky(&a, 0.0, 0.0), 0.0);
+        let (one, two): (GenericYabf<S>, GenericYabf<S>) = ((1..2).collect(), (2..3).collect());
+        assert_eq!(one.tversky(&two, 0.0, 0.0), 0.0);
+        assert_eq!(one.tversky(&two, 1.0, 1.0), 0.0);
+
+        let corpus = vec![a, b, empty];
+        for measure in [
+            Similarity::Jaccard,
+            Similarity::Dice,
+            Similarity::Cosine,
+            Similarity::Tversky {
+                alpha: 0.3,
+                beta: 0.7,
+            },
+        ]
+        .iter()
+        {
+            for query in corpus.iter() {
+                let batch: Vec<f64> = query.similarities(&corpus, *measure).collect();
+                let single: Vec<f64> = corpus
+                    .iter()
+                    .map(|other| query.similarity(other, *measure))
+                    .collect();
+                assert_eq!(batch, single);
+            }
+        }
+    }
+
+    #[test]
+    fn test_similarity() {
+        check::<Vec<u8>>();
+        check::<Vec<u32>>();
+        check::<Vec<u128>>();
+        #[cfg(feature = "smallvec")]
+        check::<smallvec::SmallVec<[u32; 4]>>();
+    }
+}
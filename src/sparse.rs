@@ -0,0 +1,632 @@
+//! A bit field for huge, scattered indices that only stores its non-zero words.
+
+use crate::bitops::impl_binary_op;
+use crate::word::{low_mask, select_in_word};
+use crate::{GenericYabf, Storage, Word};
+use std::collections::{btree_map, BTreeMap};
+use std::iter::{FromIterator, FusedIterator};
+use std::ops;
+use std::ops::{Bound, RangeBounds};
+
+/// A bit field that stores its non-zero 64 bit words in an ordered map, keyed by word
+/// index. Setting a bit close to `usize::MAX` costs a single map entry, where a dense
+/// [`Yabf`](crate::Yabf) would allocate every word below it.
+///
+/// ```
+/// # use yabf::SparseYabf;
+///
+/// let mut bf = SparseYabf::default();
+/// bf.set_bit(usize::MAX, true);
+/// bf.set_bit(7, true);
+/// assert!(bf.bit(usize::MAX));
+/// assert_eq!(bf.iter().collect::<Vec<_>>(), vec![7, usize::MAX]);
+/// assert_eq!(bf.word_count(), 2);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SparseYabf {
+    // the non-zero words, keyed by word index
+    words: BTreeMap<usize, u64>,
+}
+
+impl SparseYabf {
+    /// Construct an empty bit field
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the value of bit `n`
+    #[inline]
+    pub fn bit(&self, n: usize) -> bool {
+        self.words
+            .get(&(n / 64))
+            .map_or(false, |w| w >> (n % 64) & 1 != 0)
+    }
+
+    /// Sets bit `n` to `state`. A word is removed from the map when its last bit is cleared.
+    pub fn set_bit(&mut self, n: usize, state: bool) {
+        let mask = 1 << (n % 64);
+        if state {
+            *self.words.entry(n / 64).or_insert(0) |= mask;
+        } else if let btree_map::Entry::Occupied(mut entry) = self.words.entry(n / 64) {
+            *entry.get_mut() &= !mask;
+            if *entry.get() == 0 {
+                let _ = entry.remove();
+            }
+        }
+    }
+
+    /// Returns the number of bits set to `true`
+    pub fn count_ones(&self) -> usize {
+        self.words.values().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Returns true if no bit is set
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Returns the number of non-zero words stored
+    #[inline]
+    pub fn word_count(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Clears all bits
+    #[inline]
+    pub fn clear(&mut self) {
+        self.words.clear()
+    }
+
+    /// Returns the lowest set bit, or `None` if no bit is set
+    #[inline]
+    pub fn first_set(&self) -> Option<usize> {
+        let (i, w) = self.words.iter().next()?;
+        Some(i * 64 + w.trailing_zeros() as usize)
+    }
+
+    /// Returns the highest set bit, or `None` if no bit is set
+    #[inline]
+    pub fn last_set(&self) -> Option<usize> {
+        let (i, w) = self.words.iter().next_back()?;
+        Some(i * 64 + 63 - w.leading_zeros() as usize)
+    }
+
+    /// Returns the position of the first set bit at or above bit `n`
+    pub fn next_set(&self, n: usize) -> Option<usize> {
+        let word = n / 64;
+        if let Some(w) = self.words.get(&word) {
+            let w = w & !low_mask::<u64>(n % 64);
+            if w != 0 {
+                return Some(word * 64 + w.trailing_zeros() as usize);
+            }
+        }
+        let (i, w) = self
+            .words
+            .range((Bound::Excluded(word), Bound::Unbounded))
+            .next()?;
+        Some(i * 64 + w.trailing_zeros() as usize)
+    }
+
+    /// Returns the position of the last set bit at or below bit `n`
+    pub fn prev_set(&self, n: usize) -> Option<usize> {
+        let word = n / 64;
+        if let Some(w) = self.words.get(&word) {
+            let w = w & low_mask::<u64>(n % 64 + 1);
+            if w != 0 {
+                return Some(word * 64 + 63 - w.leading_zeros() as usize);
+            }
+        }
+        let (i, w) = self.words.range(..word).next_back()?;
+        Some(i * 64 + 63 - w.leading_zeros() as usize)
+    }
+
+    /// Returns the number of set bits strictly below bit `n`. This is an O(number of words
+    /// below `n`) operation.
+    pub fn rank(&self, n: usize) -> usize {
+        let word = n / 64;
+        let full: usize = self
+            .words
+            .range(..word)
+            .map(|(_, w)| w.count_ones() as usize)
+            .sum();
+        let partial = self
+            .words
+            .get(&word)
+            .map_or(0, |w| w & low_mask::<u64>(n % 64));
+        full + partial.count_ones() as usize
+    }
+
+    /// Returns the position of the `k`:th (zero based) set bit, or `None` if fewer than
+    /// `k + 1` bits are set.
+    pub fn select(&self, k: usize) -> Option<usize> {
+        let mut remaining = k;
+        for (i, w) in self.words.iter() {
+            let ones = w.count_ones() as usize;
+            if remaining < ones {
+                return Some(i * 64 + select_in_word(*w, remaining));
+            }
+            remaining -= ones;
+        }
+        None
+    }
+
+    /// Returns the number of set bits within `range`. Unlike the dense bit fields, an
+    /// inclusive range may end at `usize::MAX`.
+    ///
+    /// ```
+    /// # use yabf::SparseYabf;
+    ///
+    /// let bf: SparseYabf = vec![3, 40, 300, usize::MAX].into_iter().collect();
+    /// assert_eq!(bf.count_in_range(3..300), 2);
+    /// assert_eq!(bf.count_in_range(4..=300), 2);
+    /// assert_eq!(bf.count_in_range(300..=usize::MAX), 2);
+    /// assert_eq!(bf.count_in_range(..), 4);
+    /// ```
+    pub fn count_in_range<R: RangeBounds<usize>>(&self, range: R) -> usize {
+        // the first and the last bit of the range, inclusive
+        let first = match range.start_bound() {
+            Bound::Included(s) => *s,
+            Bound::Excluded(s) => match s.checked_add(1) {
+                Some(s) => s,
+                None => return 0,
+            },
+            Bound::Unbounded => 0,
+        };
+        let last = match range.end_bound() {
+            Bound::Included(e) => *e,
+            Bound::Excluded(e) => match e.checked_sub(1) {
+                Some(e) => e,
+                None => return 0,
+            },
+            Bound::Unbounded => usize::MAX,
+        };
+        if first > last {
+            return 0;
+        }
+        let (first_word, last_word) = (first / 64, last / 64);
+        self.words
+            .range(first_word..=last_word)
+            .map(|(i, w)| {
+                let mut w = *w;
+                if *i == first_word {
+                    w &= !low_mask::<u64>(first % 64);
+                }
+                if *i == last_word {
+                    w &= low_mask::<u64>(last % 64 + 1);
+                }
+                w.count_ones() as usize
+            })
+            .sum()
+    }
+
+    /// Returns an iterator over the set bit indices, from lowest to highest
+    #[inline]
+    pub fn iter(&self) -> SparseIterator<'_> {
+        SparseIterator {
+            words: self.words.iter(),
+            base: 0,
+            bits: 0,
+        }
+    }
+
+    /// Returns true if every bit set in `self` is also set in `other`
+    pub fn is_subset(&self, other: &SparseYabf) -> bool {
+        self.words
+            .iter()
+            .all(|(i, w)| other.words.get(i).map_or(false, |o| w & !o == 0))
+    }
+
+    /// Returns true if every bit set in `other` is also set in `self`
+    #[inline]
+    pub fn is_superset(&self, other: &SparseYabf) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Returns true if `self` and `other` have no set bits in common
+    #[inline]
+    pub fn is_disjoint(&self, other: &SparseYabf) -> bool {
+        self.intersecting_words(other).all(|(a, b)| a & b == 0)
+    }
+
+    /// Returns true if `self` and `other` have at least one set bit in common
+    #[inline]
+    pub fn intersects(&self, other: &SparseYabf) -> bool {
+        !self.is_disjoint(other)
+    }
+
+    /// Returns the number of bits set in both `self` and `other`
+    pub fn intersection_count(&self, other: &SparseYabf) -> usize {
+        self.intersecting_words(other)
+            .map(|(a, b)| (a & b).count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns the number of bits set in `self` or `other`
+    #[inline]
+    pub fn union_count(&self, other: &SparseYabf) -> usize {
+        self.count_ones() + other.count_ones() - self.intersection_count(other)
+    }
+
+    /// Returns the number of bits set in `self` but not in `other`
+    #[inline]
+    pub fn difference_count(&self, other: &SparseYabf) -> usize {
+        self.count_ones() - self.intersection_count(other)
+    }
+
+    /// Iterates over the pairs of words present in both `self` and `other`, looking up the
+    /// words of the larger map
+    fn intersecting_words<'a>(
+        &'a self,
+        other: &'a SparseYabf,
+    ) -> impl Iterator<Item = (u64, u64)> + 'a {
+        let (small, large) = if self.words.len() <= other.words.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        small
+            .words
+            .iter()
+            .filter_map(move |(i, w)| large.words.get(i).map(|o| (*w, *o)))
+    }
+
+    /// Returns the fraction of the words up to the last set bit that are non-zero, `1.0` if
+    /// no bit is set. A dense bit field needs about `1 / density` times the memory.
+    ///
+    /// ```
+    /// # use yabf::SparseYabf;
+    ///
+    /// let bf: SparseYabf = vec![0, 64, 64 * 3].into_iter().collect();
+    /// assert_eq!(bf.density(), 0.75);
+    /// ```
+    pub fn density(&self) -> f64 {
+        match self.words.keys().next_back() {
+            Some(last) => self.words.len() as f64 / (*last as f64 + 1.0),
+            None => 1.0,
+        }
+    }
+
+    /// Converts into a dense bit field if the [`density`](SparseYabf::density) is at least
+    /// `min_density`, otherwise returns `None`. `None` is also returned if a bit in the last
+    /// 64 is set, the bit length of the dense bit field would not fit in a `usize`.
+    ///
+    /// ```
+    /// # use yabf::{SparseYabf, Yabf};
+    ///
+    /// let bf: SparseYabf = vec![1, 100, 1 << 40].into_iter().collect();
+    /// assert!(bf.try_to_dense::<Vec<u32>>(0.1).is_none());
+    /// let bf: SparseYabf = (0..1000).step_by(3).collect();
+    /// let dense: Yabf = bf.try_to_dense(0.1).unwrap();
+    /// assert_eq!(dense.count_ones(), 334);
+    /// ```
+    pub fn try_to_dense<S: Storage>(&self, min_density: f64) -> Option<GenericYabf<S>> {
+        if self.density() >= min_density && dense_len::<S>(self).is_some() {
+            Some(self.into())
+        } else {
+            None
+        }
+    }
+}
+
+/// Iterator over the set bits of a [`SparseYabf`], from lowest to highest.
+/// Created by [`SparseYabf::iter`].
+#[derive(Clone)]
+pub struct SparseIterator<'a> {
+    words: btree_map::Iter<'a, usize, u64>,
+    // the index of the first bit of the current word, and its remaining bits
+    base: usize,
+    bits: u64,
+}
+
+impl Iterator for SparseIterator<'_> {
+    type Item = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        while self.bits == 0 {
+            let (i, w) = self.words.next()?;
+            self.base = i * 64;
+            self.bits = *w;
+        }
+        let bit = self.bits.trailing_zeros() as usize;
+        self.bits &= self.bits - 1;
+        Some(self.base + bit)
+    }
+}
+
+impl FusedIterator for SparseIterator<'_> {}
+
+impl<'a> IntoIterator for &'a SparseYabf {
+    type Item = usize;
+    type IntoIter = SparseIterator<'a>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl FromIterator<usize> for SparseYabf {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut rv = Self::default();
+        rv.extend(iter);
+        rv
+    }
+}
+
+impl Extend<usize> for SparseYabf {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for n in iter {
+            self.set_bit(n, true);
+        }
+    }
+}
+
+impl<S: Storage> From<&GenericYabf<S>> for SparseYabf {
+    fn from(bf: &GenericYabf<S>) -> Self {
+        let words = bf
+            .to_u64_words()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, w)| *w != 0)
+            .collect();
+        Self { words }
+    }
+}
+
+/// Converts into a dense bit field, which allocates every word up to the last set bit. See
+/// [`SparseYabf::try_to_dense`].
+///
+/// Panics if the number of bits up to the last set bit does not fit in a `usize`, i.e. if
+/// a bit in the last 64 is set.
+impl<S: Storage> From<&SparseYabf> for GenericYabf<S> {
+    fn from(bf: &SparseYabf) -> Self {
+        let len = dense_len::<S>(bf).expect("the bit field is too large for a dense bit field");
+        let mut internals = S::with_capacity(len);
+        internals.resize(len);
+        let words = internals.as_mut_slice();
+        // a u64 is split into several narrower words, or fills a part of a wider one
+        let per_u64 = (64 / S::Word::BITS).max(1);
+        for (i, w) in bf.words.iter() {
+            for k in 0..per_u64 {
+                let bit = i * 64 + k * S::Word::BITS;
+                words[bit / S::Word::BITS] |=
+                    S::Word::from_u64(*w >> (k * S::Word::BITS)) << (bit % S::Word::BITS);
+            }
+        }
+        Self::from_storage(internals)
+    }
+}
+
+/// Returns the number of `S::Word`s a dense copy of `bf` needs, or `None` if its bit length
+/// does not fit in a `usize`
+fn dense_len<S: Storage>(bf: &SparseYabf) -> Option<usize> {
+    let bits = bf
+        .words
+        .keys()
+        .next_back()
+        .map_or(Some(0), |last| (last + 1).checked_mul(64))?;
+    Some(bits / S::Word::BITS + (bits % S::Word::BITS != 0) as usize)
+}
+
+impl ops::BitOrAssign<&SparseYabf> for SparseYabf {
+    fn bitor_assign(&mut self, other: &SparseYabf) {
+        for (i, w) in other.words.iter() {
+            *self.words.entry(*i).or_insert(0) |= *w;
+        }
+    }
+}
+
+impl ops::BitAndAssign<&SparseYabf> for SparseYabf {
+    fn bitand_assign(&mut self, other: &SparseYabf) {
+        self.words.retain(|i, w| {
+            *w &= other.words.get(i).copied().unwrap_or(0);
+            *w != 0
+        });
+    }
+}
+
+impl ops::BitXorAssign<&SparseYabf> for SparseYabf {
+    fn bitxor_assign(&mut self, other: &SparseYabf) {
+        for (i, w) in other.words.iter() {
+            let entry = self.words.entry(*i).or_insert(0);
+            *entry ^= *w;
+            if *entry == 0 {
+                let _ = self.words.remove(i);
+            }
+        }
+    }
+}
+
+impl ops::SubAssign<&SparseYabf> for SparseYabf {
+    fn sub_assign(&mut self, other: &SparseYabf) {
+        for (i, w) in other.words.iter() {
+            if let btree_map::Entry::Occupied(mut entry) = self.words.entry(*i) {
+                *entry.get_mut() &= !*w;
+                if *entry.get() == 0 {
+                    let _ = entry.remove();
+                }
+            }
+        }
+    }
+}
+
+// `&a op &b` clones `a` and combines in place
+impl_binary_op!(
+    [] SparseYabf,
+    SparseYabf,
+    BitOr,
+    bitor,
+    BitOrAssign,
+    bitor_assign,
+    |a, b| a.clone() | b
+);
+impl_binary_op!(
+    [] SparseYabf,
+    SparseYabf,
+    BitAnd,
+    bitand,
+    BitAndAssign,
+    bitand_assign,
+    |a, b| a.clone() & b
+);
+impl_binary_op!(
+    [] SparseYabf,
+    SparseYabf,
+    BitXor,
+    bitxor,
+    BitXorAssign,
+    bitxor_assign,
+    |a, b| a.clone() ^ b
+);
+impl_binary_op!(
+    [] SparseYabf,
+    SparseYabf,
+    Sub,
+    sub,
+    SubAssign,
+    sub_assign,
+    |a, b| a.clone() - b
+);
+
+#[cfg(test)]
+mod test {
+    use super::SparseYabf;
+    use crate::Yabf;
+    use std::collections::BTreeSet;
+
+    fn sample(offset: usize) -> (SparseYabf, BTreeSet<usize>) {
+        let mut set: BTreeSet<usize> = (offset..3000).step_by(7).collect();
+        set.extend((0..40).map(|i| usize::MAX - i * 1000 - offset));
+        set.extend((1 << 40..(1 << 40) + 200).skip(offset));
+        (set.iter().copied().collect(), set)
+    }
+
+    fn check(bf: &SparseYabf, set: &BTreeSet<usize>) {
+        assert_eq!(bf.count_ones(), set.len());
+        assert!(bf.iter().eq(set.iter().copied()));
+        assert_eq!(bf.first_set(), set.iter().next().copied());
+        assert_eq!(bf.last_set(), set.iter().next_back().copied());
+        for n in set.iter() {
+            assert!(bf.bit(*n));
+            assert_eq!(bf.bit(n ^ 1), set.contains(&(n ^ 1)));
+        }
+        assert!(bf.words.values().all(|w| *w != 0));
+    }
+
+    #[test]
+    fn test_set_bit() {
+        let (mut bf, mut set) = sample(0);
+        check(&bf, &set);
+        let removed: Vec<usize> = set.iter().copied().step_by(3).collect();
+        for n in removed.iter() {
+            bf.set_bit(*n, false);
+            let _ = set.remove(n);
+        }
+        check(&bf, &set);
+        for n in set.iter() {
+            bf.set_bit(*n, false);
+        }
+        assert!(bf.is_empty());
+        assert_eq!(bf, SparseYabf::new());
+        assert_eq!(bf.first_set(), None);
+        assert_eq!(bf.density(), 1.0);
+    }
+
+    #[test]
+    fn test_set_ops() {
+        let (a, sa) = sample(0);
+        let (b, sb) = sample(5);
+        check(&(&a | &b), &(&sa | &sb));
+        check(&(&a & &b), &(&sa & &sb));
+        check(&(&a ^ &b), &(&sa ^ &sb));
+        check(&(&a - &b), &(&sa - &sb));
+        assert_eq!(a.intersection_count(&b), sa.intersection(&sb).count());
+        assert_eq!(a.union_count(&b), sa.union(&sb).count());
+        assert_eq!(a.difference_count(&b), sa.difference(&sb).count());
+        assert!(a.intersects(&b));
+        assert!((&a - &b).is_disjoint(&b));
+        assert!((&a & &b).is_subset(&a));
+        assert!((&a | &b).is_superset(&b));
+        assert!(!a.is_subset(&b));
+    }
+
+    #[test]
+    fn test_zero_words() {
+        // operations that clear every bit of a word must remove it from the map
+        let a: SparseYabf = vec![1, 64, 65, usize::MAX].into_iter().collect();
+        let b: SparseYabf = vec![1, 64, 200].into_iter().collect();
+        assert_eq!(
+            (&a & &b).words.keys().copied().collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        assert_eq!((&a ^ &b).word_count(), 3);
+        assert_eq!((&a - &b).word_count(), 2);
+        assert!((&a ^ &a).is_empty());
+        assert!((&a - &a).is_empty());
+        let mut c = a.clone();
+        c &= &SparseYabf::new();
+        assert!(c.is_empty());
+        c.set_bit(5, false);
+        assert_eq!(c.word_count(), 0);
+
+        // a dense bit field with zero words in between
+        let mut dense = Yabf::from_ranges(vec![0..1, 10_000..10_001]);
+        dense.set_bit(50_000, true);
+        dense.set_bit(50_000, false);
+        let sparse = SparseYabf::from(&dense);
+        assert_eq!(sparse.word_count(), 2);
+        assert_eq!(Yabf::from(&sparse), dense);
+    }
+
+    #[test]
+    fn test_queries() {
+        let (bf, set) = sample(3);
+        let mut probes = vec![0, 1, 64, usize::MAX - 64, usize::MAX];
+        for n in set.iter() {
+            probes.extend(vec![*n, n.wrapping_add(1), n.wrapping_sub(1)]);
+        }
+        for n in probes.iter().copied() {
+            assert_eq!(bf.next_set(n), set.range(n..).next().copied());
+            assert_eq!(bf.prev_set(n), set.range(..=n).next_back().copied());
+            assert_eq!(bf.rank(n), set.range(..n).count());
+        }
+        for (k, n) in set.iter().enumerate() {
+            assert_eq!(bf.select(k), Some(*n));
+        }
+        assert_eq!(bf.select(set.len()), None);
+        for (a, b) in probes.iter().zip(probes.iter().skip(7)) {
+            let (a, b) = (*a.min(b), *a.max(b));
+            assert_eq!(bf.count_in_range(a..b), set.range(a..b).count());
+            assert_eq!(bf.count_in_range(a..=b), set.range(a..=b).count());
+        }
+        assert_eq!(bf.count_in_range(..), set.len());
+        assert_eq!(bf.count_in_range(5..5), 0);
+    }
+
+    #[test]
+    fn test_dense_conversion() {
+        let dense: Yabf = (0..5000).step_by(3).collect();
+        let sparse = SparseYabf::from(&dense);
+        assert_eq!(sparse.count_ones(), dense.count_ones());
+        assert_eq!(sparse.density(), 1.0);
+        assert_eq!(sparse.try_to_dense::<Vec<u32>>(1.0).unwrap(), dense);
+        let dense: crate::GenericYabf<Vec<u8>> = Yabf::from(&sparse).iter().collect();
+        assert_eq!(SparseYabf::from(&dense), sparse);
+        assert_eq!(crate::GenericYabf::<Vec<u8>>::from(&sparse), dense);
+        let wide = crate::GenericYabf::<Vec<u128>>::from(&sparse);
+        assert!(wide.iter().eq(sparse.iter()));
+        assert_eq!(wide.internal_len(), (5000 + 127) / 128);
+
+        let (bf, _) = sample(0);
+        assert!(bf.density() < 1e-9);
+        assert!(bf.try_to_dense::<Vec<u32>>(0.5).is_none());
+        // the bit length of a dense copy would overflow
+        let top: SparseYabf = vec![usize::MAX].into_iter().collect();
+        assert!(top.try_to_dense::<Vec<u32>>(0.0).is_none());
+        let below: SparseYabf = vec![usize::MAX - 64].into_iter().collect();
+        assert_eq!(super::dense_len::<Vec<u64>>(&below), Some(usize::MAX / 64));
+    }
+}
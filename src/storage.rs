@@ -0,0 +1,230 @@
+//! The [`Storage`] trait abstracts over the container holding the words of a
+//! [`GenericYabf`](crate::GenericYabf).
+//!
+//! Implementations are provided for `std::vec::Vec<W>` and, with the `smallvec` feature,
+//! `smallvec::SmallVec<[W; N]>`. Other containers (arena backed slices, fixed size arrays
+//! etc.) can be used by implementing this trait.
+
+use crate::Word;
+
+/// A container of words that can be used as backing storage of a bit field.
+///
+/// The words are always addressed through [`Storage::as_slice`] and
+/// [`Storage::as_mut_slice`], the length of that slice is the number of words currently in use.
+/// Storages that can not grow (e.g. fixed size arrays) should panic in [`Storage::resize`]
+/// if the requested length is larger than what they can hold.
+pub trait Storage {
+    /// The word type the bits are packed into
+    type Word: Word;
+
+    /// The name used by the `Debug` output of the bit field
+    const NAME: &'static str = "Yabf";
+
+    /// Construct an empty storage with room for at least `words` words.
+    fn with_capacity(words: usize) -> Self;
+
+    /// The words currently in use
+    fn as_slice(&self) -> &[Self::Word];
+
+    /// The words currently in use, mutable
+    fn as_mut_slice(&mut self) -> &mut [Self::Word];
+
+    /// The number of words the storage can hold without reallocating
+    fn capacity(&self) -> usize;
+
+    /// Reserve room for at least `additional` more words.
+    fn reserve(&mut self, additional: usize);
+
+    /// Resize the storage to `new_len` words. New words are set to zero.
+    fn resize(&mut self, new_len: usize);
+
+    /// Remove all words from the storage.
+    fn clear(&mut self);
+
+    /// Release unused capacity. The default implementation does nothing.
+    #[inline]
+    fn shrink_to_fit(&mut self) {}
+
+    /// Called by the bit field after an operation that may have cleared bits. The default
+    /// implementation does nothing, see [`AutoTrim`] for a storage that uses it.
+    #[inline]
+    fn bits_cleared(&mut self) {}
+}
+
+/// Number of trailing words of `words` that are all zero
+#[inline]
+pub(crate) fn trailing_zero_words<W: Word>(words: &[W]) -> usize {
+    words.iter().rev().take_while(|w| **w == W::ZERO).count()
+}
+
+impl<W: Word> Storage for Vec<W> {
+    type Word = W;
+
+    #[inline]
+    fn with_capacity(words: usize) -> Self {
+        Vec::with_capacity(words)
+    }
+
+    #[inline]
+    fn as_slice(&self) -> &[W] {
+        self
+    }
+
+    #[inline]
+    fn as_mut_slice(&mut self) -> &mut [W] {
+        self
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        Vec::capacity(self)
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        Vec::reserve(self, additional)
+    }
+
+    #[inline]
+    fn resize(&mut self, new_len: usize) {
+        Vec::resize(self, new_len, W::ZERO)
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        Vec::clear(self)
+    }
+
+    #[inline]
+    fn shrink_to_fit(&mut self) {
+        Vec::shrink_to_fit(self)
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl<A> Storage for smallvec::SmallVec<A>
+where
+    A: smallvec::Array,
+    A::Item: Word,
+{
+    type Word = A::Item;
+
+    const NAME: &'static str = "SmallYabf";
+
+    #[inline]
+    fn with_capacity(words: usize) -> Self {
+        smallvec::SmallVec::with_capacity(words)
+    }
+
+    #[inline]
+    fn as_slice(&self) -> &[A::Item] {
+        self
+    }
+
+    #[inline]
+    fn as_mut_slice(&mut self) -> &mut [A::Item] {
+        self
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        smallvec::SmallVec::capacity(self)
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        smallvec::SmallVec::reserve(self, additional)
+    }
+
+    #[inline]
+    fn resize(&mut self, new_len: usize) {
+        smallvec::SmallVec::resize(self, new_len, A::Item::ZERO)
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        smallvec::SmallVec::clear(self)
+    }
+
+    /// Moves the words back to inline storage if they fit.
+    #[inline]
+    fn shrink_to_fit(&mut self) {
+        smallvec::SmallVec::shrink_to_fit(self)
+    }
+}
+
+/// A storage wrapper that trims trailing zero words whenever bits are cleared, and releases
+/// capacity once less than a quarter of it is used.
+///
+/// Wrapping a `SmallVec` means that a bit field that once spilled to the heap falls back to
+/// inline storage when the high bits are cleared again, see
+/// [`AutoTrimSmallYabf`](crate::AutoTrimSmallYabf).
+///
+/// ```
+/// # use yabf::{AutoTrim, GenericYabf};
+///
+/// let mut bf = GenericYabf::<AutoTrim<Vec<u32>>>::default();
+/// bf.set_bit(100_000, true);
+/// bf.set_bit(100_000, false);
+/// assert_eq!(bf.internal_len(), 0);
+/// assert!(bf.capacity() < 100_000);
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct AutoTrim<S>(pub S);
+
+impl<S: Storage> Storage for AutoTrim<S> {
+    type Word = S::Word;
+
+    const NAME: &'static str = S::NAME;
+
+    #[inline]
+    fn with_capacity(words: usize) -> Self {
+        AutoTrim(S::with_capacity(words))
+    }
+
+    #[inline]
+    fn as_slice(&self) -> &[S::Word] {
+        self.0.as_slice()
+    }
+
+    #[inline]
+    fn as_mut_slice(&mut self) -> &mut [S::Word] {
+        self.0.as_mut_slice()
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional)
+    }
+
+    #[inline]
+    fn resize(&mut self, new_len: usize) {
+        self.0.resize(new_len)
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.0.clear()
+    }
+
+    #[inline]
+    fn shrink_to_fit(&mut self) {
+        self.0.shrink_to_fit()
+    }
+
+    fn bits_cleared(&mut self) {
+        let len = self.0.as_slice().len();
+        let zeros = trailing_zero_words(self.0.as_slice());
+        if zeros > 0 {
+            self.0.resize(len - zeros);
+        }
+        if (len - zeros) * 4 <= self.0.capacity() {
+            self.0.shrink_to_fit();
+        }
+    }
+}
@@ -4,14 +4,14 @@ extern crate rand_chacha;
 use fnv::FnvHashSet;
 use itertools::Itertools;
 use rand::{Rng, SeedableRng};
-#[cfg(feature = "impl_smallvec")]
+#[cfg(feature = "smallvec")]
 use yabf::SmallYabf;
 use yabf::Yabf;
 
 fn main() {
     let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(38);
     let mut q = Yabf::with_capacity(1024);
-    #[cfg(feature = "impl_smallvec")]
+    #[cfg(feature = "smallvec")]
     let mut q1 = SmallYabf::with_capacity(1024);
 
     let mut in_q = FnvHashSet::<usize>::default();
@@ -24,7 +24,7 @@ fn main() {
     let min_size = 7;
 
     println!("running stress test with std::vec::Vec");
-    #[cfg(feature = "impl_smallvec")]
+    #[cfg(feature = "smallvec")]
     println!("...and with Smallvec");
 
     loop {
@@ -59,7 +59,7 @@ fn main() {
         println!("Adding {:?}", to_add);
         for key in to_add.iter() {
             q.set_bit(*key, true);
-            #[cfg(feature = "impl_smallvec")]
+            #[cfg(feature = "smallvec")]
             q1.set_bit(*key, true);
             in_q.insert(*key);
         }
@@ -71,7 +71,7 @@ fn main() {
                 println!("Error: {} should not be set", key);
                 panic!();
             }
-            #[cfg(feature = "impl_smallvec")]
+            #[cfg(feature = "smallvec")]
             {
                 q1.set_bit(*key, false);
                 if q1.bit(*key) {
@@ -90,43 +90,41 @@ fn main() {
                 println!("{:?}", q);
                 panic!();
             }
-            #[cfg(feature = "impl_smallvec")]
+            #[cfg(feature = "smallvec")]
             if !q1.bit(*key) {
                 println!("Error: {} was not set", key);
                 println!("{:?}", q);
                 panic!();
             }
             let another_key = key + 1;
-            if !in_q.contains(&another_key) {
-                if q.bit(another_key) {
-                    println!("Error: {} should not be set", another_key);
-                    println!("{:?}", q);
-                    panic!();
-                }
-                #[cfg(feature = "impl_smallvec")]
-                if q1.bit(another_key) {
-                    println!("Error: {} should not be set", another_key);
-                    println!("{:?}", q);
-                    panic!();
-                }
+            if !in_q.contains(&another_key) && q.bit(another_key) {
+                println!("Error: {} should not be set", another_key);
+                println!("{:?}", q);
+                panic!();
+            }
+            #[cfg(feature = "smallvec")]
+            if !in_q.contains(&another_key) && q1.bit(another_key) {
+                println!("Error: {} should not be set", another_key);
+                println!("{:?}", q);
+                panic!();
             }
         }
 
         assert_eq!(
             in_q.iter()
                 .sorted_unstable()
-                .map(|x| *x)
+                .copied()
                 .collect::<Vec<usize>>(),
-            q.into_iter().collect::<Vec<usize>>()
+            q.iter().collect::<Vec<usize>>()
         );
 
-        #[cfg(feature = "impl_smallvec")]
+        #[cfg(feature = "smallvec")]
         assert_eq!(
             in_q.iter()
                 .sorted_unstable()
-                .map(|x| *x)
+                .copied()
                 .collect::<Vec<usize>>(),
-            q1.into_iter().collect::<Vec<usize>>()
+            q1.iter().collect::<Vec<usize>>()
         );
         print!(
             "**** loop {}, transactions {} ***** vec.len {} vec.capacity {}",
@@ -135,7 +133,7 @@ fn main() {
             q.internal_len(),
             q.capacity()
         );
-        #[cfg(feature = "impl_smallvec")]
+        #[cfg(feature = "smallvec")]
         print!(
             "  smallvec.len {} smallvec.capacity {}",
             q1.internal_len(),
@@ -0,0 +1,431 @@
+//! Text formats: binary and hexadecimal numbers, and lists of set bit indices.
+//!
+//! | format          | printed by                       | example    | parsed by                        |
+//! |-----------------|----------------------------------|------------|----------------------------------|
+//! | binary          | `{:b}`, `{:#b}`                  | `0b100010` | [`GenericYabf::from_binary_str`] |
+//! | hexadecimal     | `{:x}`, `{:X}`, `{:#x}`, `{:#X}` | `0x22`     | [`GenericYabf::from_hex_str`]    |
+//! | list of indices | `{}`                             | `{1, 5}`   | [`GenericYabf::from_index_list`] |
+//!
+//! The numbers are printed most significant digit first, without leading zeros. `FromStr`
+//! accepts every format as long as the numbers carry their `0b` or `0x` prefix.
+
+use crate::{GenericYabf, Storage, Word};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// The errors reported when parsing a bit field from text. Positions are byte offsets into
+/// the parsed string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// The string is empty or only holds white space
+    Empty,
+    /// A digit is not valid in the radix of the number
+    InvalidDigit(usize),
+    /// An index does not fit in a `usize`
+    Overflow(usize),
+    /// A range is reversed, or its stride is malformed
+    InvalidRange(usize),
+    /// Something else was expected at this position
+    Unexpected {
+        /// The position of the unexpected input
+        position: usize,
+        /// A description of the expected input
+        expected: &'static str,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "cannot parse a bit field from an empty string"),
+            ParseError::InvalidDigit(p) => write!(f, "invalid digit at position {}", p),
+            ParseError::Overflow(p) => write!(f, "index too large at position {}", p),
+            ParseError::InvalidRange(p) => write!(f, "invalid range at position {}", p),
+            ParseError::Unexpected { position, expected } => {
+                write!(f, "expected {} at position {}", expected, position)
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+impl<S: Storage> GenericYabf<S> {
+    /// Writes the significant words with `digit`, most significant first, as an integer.
+    fn fmt_radix(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        prefix: &str,
+        digit: DigitFmt<S::Word>,
+    ) -> fmt::Result {
+        let words = self.significant_words();
+        let mut rv = String::new();
+        for (n, w) in words.iter().rev().enumerate() {
+            digit(&mut rv, *w, n > 0);
+        }
+        if rv.is_empty() {
+            rv.push('0');
+        }
+        f.pad_integral(true, prefix, &rv)
+    }
+
+    /// Parses a number of `bits` bits per digit, most significant digit first. The number
+    /// may start with `prefix` and `_` may be used to separate digits.
+    fn from_radix_str(s: &str, prefix: &str, bits: usize) -> Result<Self, ParseError> {
+        let offset = s.len() - s.trim_start().len();
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(ParseError::Empty);
+        }
+        let (offset, digits) = match s.get(..2) {
+            Some(p) if p.eq_ignore_ascii_case(prefix) => (offset + 2, &s[2..]),
+            _ => (offset, s),
+        };
+        let radix = 1 << bits;
+        let mut bytes = vec![0_u8; (digits.len() * bits + 7) / 8];
+        let mut pos = 0;
+        for (i, c) in digits.char_indices().rev() {
+            if c == '_' {
+                continue;
+            }
+            let v = c
+                .to_digit(radix)
+                .ok_or(ParseError::InvalidDigit(offset + i))?;
+            bytes[pos / 8] |= (v as u8) << (pos % 8);
+            pos += bits;
+        }
+        if pos == 0 {
+            return Err(ParseError::Unexpected {
+                position: offset + digits.len(),
+                expected: "a digit",
+            });
+        }
+        Ok(Self::from_bytes_le(&bytes))
+    }
+
+    /// Parses a binary number, the inverse of the `{:b}` and `{:#b}` formats. The `0b` prefix
+    /// is optional and `_` may be used to separate digits.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let bf = Yabf::from_binary_str("0b1000_0010").unwrap();
+    /// assert_eq!(bf.iter().collect::<Vec<_>>(), vec![1, 7]);
+    /// assert_eq!(format!("{:b}", bf), "10000010");
+    /// assert_eq!(format!("{:#b}", bf), "0b10000010");
+    /// ```
+    pub fn from_binary_str(s: &str) -> Result<Self, ParseError> {
+        Self::from_radix_str(s, "0b", 1)
+    }
+
+    /// Parses a hexadecimal number, the inverse of the `{:x}`, `{:X}`, `{:#x}` and `{:#X}`
+    /// formats. The `0x` prefix is optional and `_` may be used to separate digits.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let bf = Yabf::from_hex_str("0x1_0000_0001").unwrap();
+    /// assert_eq!(bf.iter().collect::<Vec<_>>(), vec![0, 32]);
+    /// assert_eq!(format!("{:x}", bf), "100000001");
+    /// assert_eq!(format!("{:#X}", bf), "0x100000001");
+    /// ```
+    pub fn from_hex_str(s: &str) -> Result<Self, ParseError> {
+        Self::from_radix_str(s, "0x", 4)
+    }
+
+    /// Parses a list of indices, the inverse of the `Display` format. The indices are
+    /// separated by commas and enclosed in braces, they do not need to be ordered.
+    ///
+    /// ```
+    /// # use yabf::Yabf;
+    ///
+    /// let bf = Yabf::from_index_list("{ 40, 3 ,7 }").unwrap();
+    /// assert_eq!(bf.to_string(), "{3, 7, 40}");
+    /// ```
+    pub fn from_index_list(s: &str) -> Result<Self, ParseError> {
+        let offset = s.len() - s.trim_start().len();
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(ParseError::Empty);
+        }
+        if !s.starts_with('{') {
+            return Err(ParseError::Unexpected {
+                position: offset,
+                expected: "`{`",
+            });
+        }
+        if s.len() < 2 || !s.ends_with('}') {
+            return Err(ParseError::Unexpected {
+                position: offset + s.len(),
+                expected: "`}`",
+            });
+        }
+        let inner = &s[1..s.len() - 1];
+        let mut rv = Self::with_capacity(0);
+        if inner.trim().is_empty() {
+            return Ok(rv);
+        }
+        let mut offset = offset + 1;
+        for token in inner.split(',') {
+            rv.set_bit(parse_index(token, offset)?, true);
+            offset += token.len() + 1;
+        }
+        Ok(rv)
+    }
+}
+
+/// Appends the digits of a word to a string, zero padded to the full word if the flag is set
+type DigitFmt<W> = fn(&mut String, W, bool);
+
+/// Parses a decimal index surrounded by optional white space, `offset` is the position of
+/// `token` in the parsed string.
+pub(crate) fn parse_index(token: &str, offset: usize) -> Result<usize, ParseError> {
+    let position = offset + token.len() - token.trim_start().len();
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(ParseError::Unexpected {
+            position,
+            expected: "an index",
+        });
+    }
+    if let Some(i) = token.bytes().position(|b| !b.is_ascii_digit()) {
+        return Err(ParseError::InvalidDigit(position + i));
+    }
+    token.parse().map_err(|_| ParseError::Overflow(position))
+}
+
+/// ```
+/// # use yabf::Yabf;
+///
+/// let bf = Yabf::from_ranges(vec![0..1, 34..35]);
+/// assert_eq!(format!("{:b}", bf), "10000000000000000000000000000000001");
+/// assert_eq!(format!("{:#b}", Yabf::default()), "0b0");
+/// ```
+impl<S: Storage> fmt::Binary for GenericYabf<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_radix(f, "0b", |s, w, pad| {
+            use std::fmt::Write;
+            let _ = if pad {
+                write!(s, "{:01$b}", w, S::Word::BITS)
+            } else {
+                write!(s, "{:b}", w)
+            };
+        })
+    }
+}
+
+/// ```
+/// # use yabf::Yabf;
+///
+/// let bf = Yabf::from_ranges(vec![4..8, 32..33]);
+/// assert_eq!(format!("{:x}", bf), "1000000f0");
+/// assert_eq!(format!("{:#014x}", bf), "0x0001000000f0");
+/// ```
+impl<S: Storage> fmt::LowerHex for GenericYabf<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_radix(f, "0x", |s, w, pad| {
+            use std::fmt::Write;
+            let _ = if pad {
+                write!(s, "{:01$x}", w, S::Word::BITS / 4)
+            } else {
+                write!(s, "{:x}", w)
+            };
+        })
+    }
+}
+
+/// ```
+/// # use yabf::Yabf;
+///
+/// let bf = Yabf::from_ranges(vec![4..8, 32..33]);
+/// assert_eq!(format!("{:#X}", bf), "0x1000000F0");
+/// ```
+impl<S: Storage> fmt::UpperHex for GenericYabf<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_radix(f, "0x", |s, w, pad| {
+            use std::fmt::Write;
+            let _ = if pad {
+                write!(s, "{:01$X}", w, S::Word::BITS / 4)
+            } else {
+                write!(s, "{:X}", w)
+            };
+        })
+    }
+}
+
+/// Lists the indices of the set bits, e.g. `{1, 5, 6}`.
+///
+/// ```
+/// # use yabf::Yabf;
+///
+/// let bf = Yabf::from_ranges(vec![1..2, 5..7]);
+/// assert_eq!(bf.to_string(), "{1, 5, 6}");
+/// assert_eq!(Yabf::default().to_string(), "{}");
+/// ```
+impl<S: Storage> fmt::Display for GenericYabf<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("{")?;
+        for (n, i) in self.iter().enumerate() {
+            if n > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", i)?;
+        }
+        f.write_str("}")
+    }
+}
+
+/// Parses a list of indices (`{1, 5}`), a binary number with the `0b` prefix or a
+/// hexadecimal number with the `0x` prefix.
+///
+/// ```
+/// # use yabf::Yabf;
+///
+/// let bf: Yabf = "{1, 5}".parse().unwrap();
+/// assert_eq!(bf, "0b100010".parse::<Yabf>().unwrap());
+/// assert_eq!(bf, "0x22".parse::<Yabf>().unwrap());
+/// assert_eq!(bf, format!("{:#x}", bf).parse::<Yabf>().unwrap());
+/// assert!("22".parse::<Yabf>().is_err());
+/// ```
+impl<S: Storage> FromStr for GenericYabf<S> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        let trimmed = s.trim_start();
+        let prefix = trimmed.get(..2).map(|p| p.to_ascii_lowercase());
+        match prefix.as_deref() {
+            _ if trimmed.starts_with('{') => Self::from_index_list(s),
+            Some("0b") => Self::from_binary_str(s),
+            Some("0x") => Self::from_hex_str(s),
+            _ if trimmed.is_empty() => Err(ParseError::Empty),
+            _ => Err(ParseError::Unexpected {
+                position: s.len() - trimmed.len(),
+                expected: "`{`, `0b` or `0x`",
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ParseError;
+    use crate::GenericYabf;
+
+    fn check<S: crate::Storage + Default>() {
+        let samples: Vec<GenericYabf<S>> = vec![
+            GenericYabf::default(),
+            (0..1).collect(),
+            (0..200).step_by(7).collect(),
+            [3_usize, 64, 65, 127, 128, 1000].iter().copied().collect(),
+        ];
+        for bf in samples.iter() {
+            for text in [
+                format!("{:#b}", bf),
+                format!("{:#x}", bf),
+                format!("{:#X}", bf),
+                format!("{}", bf),
+            ]
+            .iter()
+            {
+                assert_eq!(&text.parse::<GenericYabf<S>>().unwrap(), bf, "{}", text);
+            }
+            assert_eq!(
+                &GenericYabf::<S>::from_binary_str(&format!("{:b}", bf)).unwrap(),
+                bf
+            );
+            assert_eq!(
+                &GenericYabf::<S>::from_hex_str(&format!("{:x}", bf)).unwrap(),
+                bf
+            );
+            assert_eq!(
+                &GenericYabf::<S>::from_hex_str(&format!("{:X}", bf)).unwrap(),
+                bf
+            );
+        }
+        let bf = &samples[3];
+        assert_eq!(format!("{:x}", bf), format!("{:x}", num_bits(bf)));
+        assert_eq!(format!("{:b}", bf), format!("{:b}", num_bits(bf)));
+        assert_eq!(bf.to_string(), "{3, 64, 65, 127, 128, 1000}");
+    }
+
+    /// The bit field as a big integer
+    fn num_bits<S: crate::Storage>(bf: &GenericYabf<S>) -> num_bigint::BigUint {
+        bf.iter()
+            .map(|i| num_bigint::BigUint::from(1_u8) << i)
+            .sum()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        check::<Vec<u8>>();
+        check::<Vec<u32>>();
+        check::<Vec<u64>>();
+        check::<Vec<u128>>();
+        #[cfg(feature = "smallvec")]
+        check::<smallvec::SmallVec<[u32; 4]>>();
+    }
+
+    #[test]
+    fn test_leading_zeros() {
+        let bf = crate::Yabf::from_hex_str("0x0000_0000_0000_0010").unwrap();
+        assert_eq!(bf.iter().collect::<Vec<_>>(), vec![4]);
+        assert_eq!(format!("{:x}", bf), "10");
+        assert_eq!(format!("{:>6x}", bf), "    10");
+        assert_eq!(format!("{:#06b}", bf), "0b10000");
+        assert_eq!(format!("{:x}", crate::Yabf::with_capacity(100)), "0");
+        assert!(crate::Yabf::from_binary_str("0").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_errors() {
+        type Y = crate::Yabf;
+        assert_eq!(Y::from_hex_str(""), Err(ParseError::Empty));
+        assert_eq!(" ".parse::<Y>(), Err(ParseError::Empty));
+        assert_eq!(Y::from_hex_str("0x12g4"), Err(ParseError::InvalidDigit(4)));
+        assert_eq!(
+            Y::from_binary_str(" 0b102"),
+            Err(ParseError::InvalidDigit(5))
+        );
+        assert_eq!(
+            Y::from_hex_str("0x"),
+            Err(ParseError::Unexpected {
+                position: 2,
+                expected: "a digit"
+            })
+        );
+        assert_eq!(
+            Y::from_index_list("{1, 2"),
+            Err(ParseError::Unexpected {
+                position: 5,
+                expected: "`}`"
+            })
+        );
+        assert_eq!(
+            Y::from_index_list("{1,,2}"),
+            Err(ParseError::Unexpected {
+                position: 3,
+                expected: "an index"
+            })
+        );
+        assert_eq!(
+            Y::from_index_list("{1, -2}"),
+            Err(ParseError::InvalidDigit(4))
+        );
+        assert_eq!(
+            Y::from_index_list("{99999999999999999999999}"),
+            Err(ParseError::Overflow(1))
+        );
+        assert_eq!(
+            "12".parse::<Y>(),
+            Err(ParseError::Unexpected {
+                position: 0,
+                expected: "`{`, `0b` or `0x`"
+            })
+        );
+        assert_eq!(
+            ParseError::InvalidDigit(3).to_string(),
+            "invalid digit at position 3"
+        );
+    }
+}
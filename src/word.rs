@@ -0,0 +1,113 @@
+//! The [`Word`] trait abstracts over the unsigned integer type the bits are packed into.
+
+use core::fmt;
+use core::hash::Hash;
+use std::ops;
+
+/// An unsigned integer type that can be used as the word type of a bit field.
+///
+/// Implemented for `u8`, `u16`, `u32`, `u64` and `u128`.
+pub trait Word:
+    Copy
+    + Default
+    + Eq
+    + Ord
+    + Hash
+    + fmt::Debug
+    + fmt::Binary
+    + fmt::LowerHex
+    + fmt::UpperHex
+    + ops::BitAnd<Output = Self>
+    + ops::BitOr<Output = Self>
+    + ops::BitXor<Output = Self>
+    + ops::Not<Output = Self>
+    + ops::BitAndAssign
+    + ops::BitOrAssign
+    + ops::BitXorAssign
+    + ops::Shl<usize, Output = Self>
+    + ops::Shr<usize, Output = Self>
+{
+    /// The number of bits in the word
+    const BITS: usize;
+    /// A word with all bits cleared
+    const ZERO: Self;
+    /// A word with only the lowest bit set
+    const ONE: Self;
+    /// A word with all bits set
+    const MAX: Self;
+
+    /// Returns the number of ones in the binary representation of the word.
+    fn count_ones(self) -> u32;
+
+    /// Returns the number of trailing zeros in the binary representation of the word.
+    fn trailing_zeros(self) -> u32;
+
+    /// Returns the number of leading zeros in the binary representation of the word.
+    fn leading_zeros(self) -> u32;
+
+    /// Converts a `u64` into a word, truncating the high bits if the word is smaller.
+    fn from_u64(value: u64) -> Self;
+
+    /// Converts the word into a `u64`, truncating the high bits if the word is larger.
+    fn to_u64(self) -> u64;
+}
+
+macro_rules! impl_word {
+    ($($t:ty),*) => {
+        $(
+            impl Word for $t {
+                const BITS: usize = <$t>::BITS as usize;
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+                const MAX: Self = <$t>::MAX;
+
+                #[inline]
+                fn count_ones(self) -> u32 {
+                    <$t>::count_ones(self)
+                }
+
+                #[inline]
+                fn trailing_zeros(self) -> u32 {
+                    <$t>::trailing_zeros(self)
+                }
+
+                #[inline]
+                fn leading_zeros(self) -> u32 {
+                    <$t>::leading_zeros(self)
+                }
+
+                #[inline]
+                fn from_u64(value: u64) -> Self {
+                    value as $t
+                }
+
+                #[inline]
+                fn to_u64(self) -> u64 {
+                    self as u64
+                }
+            }
+        )*
+    };
+}
+
+impl_word!(u8, u16, u32, u64, u128);
+
+/// Returns a word with the lowest `n` bits set, `n` must be in the range `0..=W::BITS`.
+#[inline]
+pub(crate) fn low_mask<W: Word>(n: usize) -> W {
+    if n == 0 {
+        W::ZERO
+    } else {
+        W::MAX >> (W::BITS - n)
+    }
+}
+
+/// Returns the position of the `k`:th (zero based) set bit in `word`, `k` must be less than
+/// `word.count_ones()`.
+#[inline]
+pub(crate) fn select_in_word<W: Word>(mut word: W, k: usize) -> usize {
+    for _ in 0..k {
+        word ^= W::ONE << (word.trailing_zeros() as usize);
+    }
+    word.trailing_zeros() as usize
+}